@@ -1,56 +1,163 @@
-use crate::error::Error;
+use crate::error::{Error, ModelErrorKind, PolicyError};
+use crate::model::{model_error, RuleSet};
 use crate::rbac::RoleManager;
 
-#[derive(Debug)]
+/// A definition of a model, e.g. `p = sub, obj, act`, with the policy rules of its type.
+///
+/// Assertions are owned by a `Model` and only change through its methods.
+#[derive(Debug, PartialEq)]
 pub struct Assertion {
-    pub key: String,
-    pub value: String,
-    pub tokens: Vec<String>,
-    pub policy: Vec<Vec<String>>,
+    pub(crate) key: String,
+    pub(crate) value: String,
+    pub(crate) tokens: Vec<String>,
+    pub(crate) policy: RuleSet,
 }
 
 impl Assertion {
-    pub fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Assertion {
             key: "".to_string(),
             value: "".to_string(),
             tokens: Vec::new(),
-            policy: Vec::new(),
+            policy: RuleSet::new(),
         }
     }
 
+    /// Get the key of the definition, e.g. `p2`.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Get the value of the definition, with the tokens of matchers and effects escaped to `r_sub`.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get the tokens of a request or policy definition, e.g. `r_sub`, `r_obj`, `r_act`.
+    pub fn tokens(&self) -> &[String] {
+        &self.tokens
+    }
+
+    /// Get the policy rules of this type.
+    pub fn policy(&self) -> &[Vec<String>] {
+        self.policy.rules()
+    }
+
+    /// Add the links of every grouping rule to `role_manager`.
+    ///
+    /// The fields after the two names (e.g. the domain of `g = _, _, _`) are passed to the role
+    /// manager, values beyond the role definition are custom data and are ignored.
     pub fn build_role_links<RM: RoleManager + Send + 'static>(&mut self, role_manager: &mut RM) -> Result<(), Error> {
-        let count = self.value.matches('_').count();
-        for rule in &self.policy {
-            if count < 2 {
-                // the number of '_' characters in a role definition must be at least 2
-                dbg!(count);
-                dbg!(&self);
-                dbg!(&rule);
-                panic!("count < 2");
-                return Err(Error::ParsingFailure);
-            } else if rule.len() < count {
-                // grouping policy elements does not match the role definition.
-                dbg!(count);
-                dbg!(&rule);
-                panic!("rule.len() < count");
-                return Err(Error::ParsingFailure);
-            }
+        self.add_role_links(role_manager, self.policy.rules())
+    }
+
+    /// Add the links of the grouping `rules` to `role_manager`, leaving existing links untouched.
+    pub fn add_role_links<RM: RoleManager + Send + 'static>(
+        &self,
+        role_manager: &mut RM,
+        rules: &[Vec<String>],
+    ) -> Result<(), Error> {
+        let count = self.role_link_count(rules)?;
+        for rule in rules {
+            let fields = self.link_fields(rule, count)?;
+            role_manager.add_link_with_fields(&rule[0], &rule[1], &fields)?;
+        }
+        Ok(())
+    }
 
-            if count == 2 {
-                role_manager.add_link(&rule[0], &rule[1], None)?;
-            } else if count == 3 {
-                role_manager.add_link(&rule[0], &rule[1], Some(&rule[2]))?;
-            } else if count == 4 {
-                // the original code does something here, but I'm not sure if it's supposed to work.
-                dbg!(&rule);
-                panic!("build_role_links count = 4");
-                return Err(Error::ParsingFailure);
-            } else {
-                panic!("build_role_links count > 4");
-                return Err(Error::ParsingFailure);
+    /// Delete the links of the grouping `rules` from `role_manager`.
+    ///
+    /// The rules must already be removed from the policy, a link that is still implied by a remaining
+    /// rule (e.g. one that only differs in its custom data) is kept.
+    pub fn delete_role_links<RM: RoleManager + Send + 'static>(
+        &self,
+        role_manager: &mut RM,
+        rules: &[Vec<String>],
+    ) -> Result<(), Error> {
+        let count = self.role_link_count(rules)?;
+        for rule in rules {
+            let fields = self.link_fields(rule, count)?;
+            let still_linked = self
+                .policy()
+                .iter()
+                .any(|p| p.len() >= count && p[..count] == rule[..count]);
+            if !still_linked {
+                role_manager.delete_link_with_fields(&rule[0], &rule[1], &fields)?;
             }
         }
         Ok(())
     }
+
+    /// Get the number of fields of a role link, i.e. the number of '_' in the role definition.
+    fn role_link_count(&self, rules: &[Vec<String>]) -> Result<usize, Error> {
+        let count = self.value.matches('_').count();
+        if count < 2 && !rules.is_empty() {
+            // the number of '_' characters in a role definition must be at least 2
+            let reason = format!("a role definition has at least 2 fields, got `{}`", self.value);
+            return Err(model_error("g", &self.key, ModelErrorKind::InvalidDefinition(reason)));
+        }
+        Ok(count)
+    }
+
+    /// Get the fields of `rule` after the two names that are part of its role link.
+    fn link_fields<'a>(&self, rule: &'a [String], count: usize) -> Result<Vec<&'a str>, Error> {
+        if rule.len() < count {
+            // grouping policy elements does not match the role definition.
+            let reason = format!(
+                "expected at least {} fields ({}), found {}",
+                count,
+                self.value,
+                rule.len()
+            );
+            return Err(PolicyError::new(&self.key, rule, None, &reason).into());
+        }
+        Ok(rule[2..count].iter().map(|f| f.as_str()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ModelError;
+    use crate::rbac::DefaultRoleManager;
+    use crate::util::to_strings;
+
+    fn grouping(value: &str, policy: &[&[&str]]) -> Assertion {
+        let mut assertion = Assertion::new();
+        assertion.key = "g".to_string();
+        assertion.value = value.to_string();
+        for rule in policy {
+            assertion.policy.insert(to_strings(rule));
+        }
+        assertion
+    }
+
+    #[test]
+    fn test_build_role_links_with_extra_fields() {
+        let mut assertion = grouping(
+            "_, _, _, _",
+            &[&["alice", "admin", "domain1", "tenant1"], &["bob", "admin", "domain1", "tenant2"]],
+        );
+        let mut manager = DefaultRoleManager::new(10);
+        assertion.build_role_links(&mut manager).unwrap();
+
+        assert_eq!(manager.has_link_with_fields("alice", "admin", &["domain1", "tenant1"]), true);
+        assert_eq!(manager.has_link_with_fields("alice", "admin", &["domain1", "tenant2"]), false);
+        assert_eq!(manager.has_link_with_fields("bob", "admin", &["domain1", "tenant2"]), true);
+        assert_eq!(manager.has_link("alice", "admin", Some("domain1")), false);
+    }
+
+    #[test]
+    fn test_build_role_links_errors() {
+        let mut manager = DefaultRoleManager::new(10);
+
+        let mut assertion = grouping("_", &[&["alice", "admin"]]);
+        assert!(matches!(
+            assertion.build_role_links(&mut manager),
+            Err(Error::Model(ModelError { kind: ModelErrorKind::InvalidDefinition(_), .. }))
+        ));
+
+        let mut assertion = grouping("_, _, _", &[&["alice", "admin"]]);
+        assert!(matches!(assertion.build_role_links(&mut manager), Err(Error::Policy(_))));
+    }
 }
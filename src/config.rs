@@ -1,24 +1,19 @@
-// Note: multi-line values are not currently supported.
-
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::path::Path;
 
-use lazy_static::lazy_static;
-use regex::Regex;
-
-use crate::error::Error;
+use crate::error::{ConfigError, Error};
+use crate::util::comment_start;
 
 const DEFAULT_SECTION: &str = "default";
 
-lazy_static! {
-    static ref REGEX_CONFIG: Regex = Regex::new(r"^\s*([\w\.]+)\s*=\s*(.+)\s*$").unwrap();
-}
-
 /// A configuration.
 #[derive(Debug)]
 pub struct Config {
     data: HashMap<String, HashMap<String, String>>,
+    // the sections in the order they were added, to write them back in that order
+    sections: Vec<String>,
 }
 
 impl Config {
@@ -31,17 +26,32 @@ impl Config {
 
     /// Parse a configuration from a string.
     pub fn from_string(contents: &str) -> Result<Self, Error> {
-        let mut config = Config { data: HashMap::new() };
+        let mut config = Config::empty();
         config.parse(contents)?;
         Ok(config)
     }
 
+    /// Create a configuration without any value.
+    pub fn empty() -> Self {
+        Config {
+            data: HashMap::new(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Write the configuration to a file, see the `Display` implementation for the format.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
     /// Set the `value` for `key` in the configuration.
     pub fn set(&mut self, key: &str, value: &str, section: Option<&str>) {
         let section = section.unwrap_or(DEFAULT_SECTION);
 
         if !self.data.contains_key(section) {
             self.data.insert(section.to_string(), HashMap::new());
+            self.sections.push(section.to_string());
         }
         self.data
             .get_mut(section)
@@ -49,21 +59,51 @@ impl Config {
             .insert(key.to_string(), value.to_string());
     }
 
+    /// Parse `key = value` lines grouped in `[section]`s.
+    ///
+    /// Lines starting with `#` or `;` are comments, a `#` outside a string literal starts an inline
+    /// comment like in the model definitions, see `remove_comments`.
+    /// A line ending with `\` is continued on the next line. A value may be enclosed in double quotes to
+    /// keep a `#` or surrounding spaces, `\"` and `\\` are escapes inside quotes.
     fn parse(&mut self, contents: &str) -> Result<(), Error> {
-        let mut section = DEFAULT_SECTION;
-        for line in contents.lines() {
-            let line = line.trim();
-            if line.starts_with('#') || line.starts_with(';') {
+        let mut section = DEFAULT_SECTION.to_string();
+        let mut lines = contents.lines().enumerate();
+
+        while let Some((i, line)) = lines.next() {
+            let number = i + 1;
+            let mut line = line.trim().to_string();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
                 continue;
-            } else if line.starts_with('[') && line.ends_with(']') {
-                section = line.get(1..line.len() - 1).ok_or(Error::ParsingFailure)?;
+            }
+
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some((_, next)) => line = format!("{} {}", line.trim_end(), next.trim()),
+                    None => break,
+                }
+            }
+            let line = line.trim();
+            let invalid_line = || {
+                Error::Config(ConfigError::InvalidLine {
+                    line: number,
+                    text: line.to_string(),
+                })
+            };
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(invalid_line());
+                }
+                section = line[1..line.len() - 1].trim().to_string();
             } else {
-                if REGEX_CONFIG.is_match(line) {
-                    let captures = REGEX_CONFIG.captures(line).ok_or(Error::ParsingFailure)?;
-                    let key = &captures[1].trim();
-                    let value = &captures[2].trim();
-                    self.set(key, value, Some(section));
+                let pos = line.find('=').ok_or_else(invalid_line)?;
+                let key = line[..pos].trim();
+                if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+                    return Err(invalid_line());
                 }
+                let value = parse_value(line[pos + 1..].trim()).ok_or_else(invalid_line)?;
+                self.set(key, &value, Some(&section));
             }
         }
         Ok(())
@@ -92,25 +132,118 @@ impl Config {
 
     /// Get the value using the provided key and convert the value to a bool.
     pub fn bool(&self, key: &str, section: Option<&str>) -> Result<bool, Error> {
-        let value = self.get(key, section).ok_or(Error::MissingKey)?;
+        let value = self.value(key, section)?;
 
         match value {
             "true" => Ok(true),
             "false" => Ok(false),
-            _ => Err(Error::InvalidValue),
+            _ => Err(invalid_value(key, section, value)),
         }
     }
 
     /// Get the value using the provided key and convert the value to an i64.
     pub fn int64(&self, key: &str, section: Option<&str>) -> Result<i64, Error> {
-        let value = self.get(key, section).ok_or(Error::MissingKey)?;
-        value.parse().map_err(|_| Error::InvalidValue)
+        let value = self.value(key, section)?;
+        value.parse().map_err(|_| invalid_value(key, section, value))
     }
 
     /// Get the value using the provided key and convert the value to a f64.
     pub fn float64(&self, key: &str, section: Option<&str>) -> Result<f64, Error> {
-        let value = self.get(key, section).ok_or(Error::MissingKey)?;
-        value.parse().map_err(|_| Error::InvalidValue)
+        let value = self.value(key, section)?;
+        value.parse().map_err(|_| invalid_value(key, section, value))
+    }
+
+    fn value(&self, key: &str, section: Option<&str>) -> Result<&str, Error> {
+        self.get(key, section).ok_or_else(|| {
+            Error::Config(ConfigError::MissingKey {
+                section: section.unwrap_or(DEFAULT_SECTION).to_string(),
+                key: key.to_string(),
+            })
+        })
+    }
+}
+
+fn invalid_value(key: &str, section: Option<&str>, value: &str) -> Error {
+    Error::Config(ConfigError::InvalidValue {
+        section: section.unwrap_or(DEFAULT_SECTION).to_string(),
+        key: key.to_string(),
+        value: value.to_string(),
+    })
+}
+
+impl fmt::Display for Config {
+    /// Write the configuration in the format read by `Config::parse`.
+    ///
+    /// The values of the default section come first, then every section in the order it was added.
+    /// The keys of a section are sorted and values are quoted when they could not be read back as is.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let default = self.sections.iter().filter(|s| *s == DEFAULT_SECTION);
+        let others = self.sections.iter().filter(|s| *s != DEFAULT_SECTION);
+
+        for (i, section) in default.chain(others).enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            if section != DEFAULT_SECTION {
+                writeln!(f, "[{}]", section)?;
+            }
+
+            let values = &self.data[section];
+            let mut keys: Vec<&String> = values.keys().collect();
+            keys.sort();
+            for key in keys {
+                writeln!(f, "{} = {}", key, format_value(&values[key]))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Quote `value` if `parse_value` would not read it back as is.
+fn format_value(value: &str) -> String {
+    let needs_quotes = value.trim() != value
+        || value.starts_with('"')
+        || value.starts_with('#')
+        || value.ends_with('\\')
+        || comment_start(value).is_some();
+
+    if needs_quotes {
+        format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Get the value of a `key = value` line without its quotes or inline comment.
+///
+/// Returns None if a quoted value is not terminated or followed by anything but a comment.
+fn parse_value(value: &str) -> Option<String> {
+    if !value.starts_with('"') {
+        let end = comment_start(value).unwrap_or(value.len());
+        return Some(value[..end].trim_end().to_string());
+    }
+
+    let mut unquoted = String::new();
+    let mut chars = value[1..].chars();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => match chars.next()? {
+                c @ '"' | c @ '\\' => unquoted.push(c),
+                c => {
+                    unquoted.push('\\');
+                    unquoted.push(c);
+                }
+            },
+            c => unquoted.push(c),
+        }
+    }
+
+    let rest = chars.as_str().trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Some(unquoted)
+    } else {
+        None
     }
 }
 
@@ -145,4 +278,75 @@ mod tests {
         config.set("key1", "new test key", Some("other"));
         assert_eq!(config.string("key1", Some("other")).unwrap(), "new test key");
     }
+
+    #[test]
+    fn test_parse_multi_line_and_quoted_values() {
+        let config = Config::from_string(
+            r#"[matchers]
+m = g(r.sub, p.sub) && r.obj == p.obj \
+  && r.act == p.act # inline comment
+quoted = "  a # b \"c\"  " # comment
+hash = a#b
+literal = r.obj == "a #b" && r.act == 'c#d' # comment
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.string("m", Some("matchers")).unwrap(),
+            "g(r.sub, p.sub) && r.obj == p.obj && r.act == p.act"
+        );
+        assert_eq!(config.string("quoted", Some("matchers")).unwrap(), "  a # b \"c\"  ");
+        assert_eq!(config.string("hash", Some("matchers")).unwrap(), "a");
+        assert_eq!(
+            config.string("literal", Some("matchers")).unwrap(),
+            r#"r.obj == "a #b" && r.act == 'c#d'"#
+        );
+
+        let config = Config::new("examples/rbac_model_in_multi_line.conf").unwrap();
+        assert_eq!(
+            config.string("m", Some("matchers")).unwrap(),
+            "g(r.sub, p.sub) && r.obj == p.obj && r.act == p.act"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Config::from_string("[section]\nkey = value\nnot a key value\n"),
+            Err(Error::Config(ConfigError::InvalidLine { line: 3, .. }))
+        ));
+        assert!(matches!(
+            Config::from_string("\n[section\nkey = value\n"),
+            Err(Error::Config(ConfigError::InvalidLine { line: 2, .. }))
+        ));
+        assert!(matches!(
+            Config::from_string("key = \"unterminated\n"),
+            Err(Error::Config(ConfigError::InvalidLine { line: 1, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_to_string() {
+        let mut config = Config::empty();
+        config.set("url", "act.wiki", None);
+        config.set("key2", "  padded # not a comment", Some("other"));
+        config.set("key1", "a#b", Some("other"));
+        config.set("path", "C:\\dir\\", Some("other"));
+        config.set("debug", "true", None);
+
+        let text = config.to_string();
+        assert_eq!(
+            text,
+            "debug = true\nurl = act.wiki\n\n[other]\nkey1 = \"a#b\"\nkey2 = \"  padded # not a comment\"\npath = \"C:\\\\dir\\\\\"\n"
+        );
+
+        let parsed = Config::from_string(&text).unwrap();
+        assert_eq!(
+            parsed.string("key2", Some("other")).unwrap(),
+            "  padded # not a comment"
+        );
+        assert_eq!(parsed.string("path", Some("other")).unwrap(), "C:\\dir\\");
+        assert_eq!(parsed.to_string(), text);
+    }
 }
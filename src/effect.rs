@@ -1,4 +1,14 @@
-use crate::error::Error;
+use crate::error::{Error, ModelErrorKind};
+use crate::model::model_error;
+use crate::util::unescape_assertion;
+
+/// The policy effects supported by `DefaultEffector`, in the escaped notation of a model.
+pub const SUPPORTED_EFFECTS: &[&str] = &[
+    "some(where (p_eft == allow))",
+    "!some(where (p_eft == deny))",
+    "some(where (p_eft == allow)) && !some(where (p_eft == deny))",
+    "priority(p_eft) || deny",
+];
 
 #[derive(Debug, PartialEq)]
 pub enum Effect {
@@ -75,7 +85,11 @@ impl Effector for DefaultEffector {
                 }
                 Ok(result)
             }
-            _ => Err(Error::UnsupportedEffect),
+            _ => Err(model_error(
+                "e",
+                "e",
+                ModelErrorKind::UnsupportedEffect(unescape_assertion(expr)),
+            )),
         }
     }
 }
@@ -1,53 +1,98 @@
 use std::ops::DerefMut;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, RwLock};
 use std::any::Any;
 
 use eval::{to_value, Expr};
 
 use crate::effect::{DefaultEffector, Effect, Effector};
-use crate::error::Error;
+use crate::enforcer::rule_selection::{find_predicates, Predicate};
+use crate::error::{Error, MatcherError, ModelErrorKind};
 use crate::model::Model;
-use crate::model::{get_function_map, FunctionMap};
+use crate::model::{get_function_map, model_error, FunctionMap};
+use crate::persist::memory_adapter::MemoryAdapter;
 use crate::persist::Adapter;
 use crate::rbac::{DefaultRoleManager, RoleManager, MatchingFunction};
-use crate::util::builtin_operators;
+use crate::util::{builtin_operators, unescape_assertion};
 use std::collections::HashMap;
 use std::process::Output;
 
 mod internal_api;
 pub mod management_api;
 pub mod rbac_api;
+mod rule_selection;
 
 #[derive(Debug)]
 pub struct DefaultEnforcer();
 
 impl DefaultEnforcer {
     pub fn new<A: Adapter>(model: Model, policy: A) -> Result<Enforcer<A, DefaultRoleManager, DefaultEffector>, Error> {
-        Enforcer::new(model, policy, DefaultRoleManager::new(10), DefaultEffector::new())
+        Enforcer::new(model, policy, || DefaultRoleManager::new(10), DefaultEffector::new())
+    }
+
+    /// Create an Enforcer with an empty policy kept in memory, see `Enforcer::from_model`.
+    pub fn from_model(model: Model) -> Result<Enforcer<MemoryAdapter, DefaultRoleManager, DefaultEffector>, Error> {
+        Enforcer::from_model(model, || DefaultRoleManager::new(10), DefaultEffector::new())
     }
 }
 
 /// Enforcer is the main interface for authorization enforcement and policy management.
 #[derive(Debug)]
-pub struct Enforcer<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> {
+pub struct Enforcer<A: Adapter, RM: RoleManager + Send + Sync + 'static, E: Effector> {
     model: Model,
     function_map: FunctionMap,
     adapter: A,
-    role_manager: Arc<Mutex<RM>>,
+    role_managers: HashMap<String, Arc<RwLock<RM>>>,
     effector: E,
+    auto_save: bool,
     auto_build_role_links: bool,
+    // the conditions of the matcher used to select the rules a request can match
+    predicates: Vec<Predicate>,
 }
 
-impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM, E> {
+impl<A: Adapter, RM: RoleManager + Send + Sync + 'static, E: Effector> Enforcer<A, RM, E> {
     /// Create an instance of an Enforcer from a `model` and `policy`.
-    pub fn new(model: Model, policy: A, role_manager: RM, effector: E) -> Result<Enforcer<A, RM, E>, Error> {
+    ///
+    /// `role_manager` creates the role manager of each role definition of the model (`g`, `g2`, ...),
+    /// so that they are configured alike but keep their links apart.
+    ///
+    /// The model is validated first, see `Model::validate`.
+    pub fn new<F>(mut model: Model, policy: A, role_manager: F, effector: E) -> Result<Enforcer<A, RM, E>, Error>
+    where
+        F: Fn() -> RM,
+    {
+        model.validate()?;
+
+        // index the policy fields constrained by the matcher, see `candidate_positions`
+        let predicates = match model.get_assertion("m", "m") {
+            Some(matcher) => find_predicates(matcher.value()),
+            None => Vec::new(),
+        };
+        for predicate in &predicates {
+            let field = model
+                .get_tokens("p", "p")
+                .and_then(|tokens| tokens.iter().position(|token| token == predicate.policy_token()));
+            if let Some(field) = field {
+                model.add_field_index("p", "p", field);
+            }
+        }
+
+        let mut role_managers = HashMap::new();
+        role_managers.insert("g".to_string(), Arc::new(RwLock::new(role_manager())));
+        for ptype in model.get_ptypes("g") {
+            role_managers
+                .entry(ptype)
+                .or_insert_with(|| Arc::new(RwLock::new(role_manager())));
+        }
+
         let mut enforcer = Enforcer {
             model,
             function_map: get_function_map(),
             adapter: policy,
-            role_manager: Arc::new(Mutex::new(role_manager)),
+            role_managers,
             effector,
+            auto_save: true,
             auto_build_role_links: true,
+            predicates,
         };
 
         enforcer.load_policy()?;
@@ -57,9 +102,56 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
 
     /// Rebuild the role inheritance relations.
     fn build_role_links(&mut self) -> Result<(), Error> {
-        self.role_manager.lock().unwrap().clear()?;
-        self.model
-            .build_role_links(self.role_manager.lock().unwrap().deref_mut())?;
+        for (ptype, role_manager) in &self.role_managers {
+            let mut role_manager = role_manager.write().unwrap();
+            role_manager.clear()?;
+            self.model.build_role_links(ptype, role_manager.deref_mut())?;
+        }
+        Ok(())
+    }
+
+    /// Add the role links of the grouping `rules` of the role definition `ptype`.
+    fn add_role_links(&self, ptype: &str, rules: &[Vec<String>]) -> Result<(), Error> {
+        if let Some(role_manager) = self.role_managers.get(ptype) {
+            let mut role_manager = role_manager.write().unwrap();
+            self.model.add_role_links(ptype, role_manager.deref_mut(), rules)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the role links of the grouping `rules` of the role definition `ptype`.
+    fn delete_role_links(&self, ptype: &str, rules: &[Vec<String>]) -> Result<(), Error> {
+        if let Some(role_manager) = self.role_managers.get(ptype) {
+            let mut role_manager = role_manager.write().unwrap();
+            self.model.delete_role_links(ptype, role_manager.deref_mut(), rules)?;
+        }
+        Ok(())
+    }
+
+    /// Control whether policy changes made through the management API are saved to the adapter.
+    pub fn enable_auto_save(&mut self, auto_save: bool) {
+        self.auto_save = auto_save;
+    }
+
+    /// Get the role manager used by the role definition `ptype`.
+    pub(crate) fn get_role_manager(&self, ptype: &str) -> Option<&Arc<RwLock<RM>>> {
+        self.role_managers.get(ptype)
+    }
+
+    /// Set the role manager used by the `g` role definition.
+    pub fn set_role_manager(&mut self, role_manager: RM) -> Result<(), Error> {
+        self.set_named_role_manager("g", role_manager)
+    }
+
+    /// Set the role manager used by the role definition `ptype` and rebuild its role links.
+    pub fn set_named_role_manager(&mut self, ptype: &str, role_manager: RM) -> Result<(), Error> {
+        self.role_managers
+            .insert(ptype.to_string(), Arc::new(RwLock::new(role_manager)));
+
+        if self.auto_build_role_links {
+            self.build_role_links()?;
+        }
+
         Ok(())
     }
 
@@ -79,98 +171,74 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
         self.model.clear_policy();
     }
 
-    /// Decide whether `subject` can access `object` with the operation `action`.
+    /// Get the matcher `m` or the effect `e` of the model, they are required by `Model::validate`.
+    fn get_definition(&self, sec: &str) -> Result<&str, Error> {
+        match self.model.get_assertion(sec, sec) {
+            Some(assertion) => Ok(assertion.value()),
+            None => Err(model_error(sec, sec, ModelErrorKind::MissingDefinition)),
+        }
+    }
 
-    // TODO: enforce does not handle matcherResults.
+    /// Decide whether `subject` can access `object` with the operation `action`.
     pub fn enforce(&self, subject: &str, object: &str, action: &str) -> Result<bool, Error> {
-        let mut policy_effects: Vec<Effect> = vec![];
-
-        let expr_string = &self.model.data["m"]["m"].value;
-
-        for policy in &self.model.data["p"]["p"].policy {
-            let mut expr = Expr::new(expr_string.clone())
-                .value("r_sub", subject)
-                .value("r_obj", object)
-                .value("r_act", action)
-                .value("p_sub", &policy[0])
-                .value("p_obj", &policy[1])
-                .value("p_act", &policy[2])
-                .function("keyMatch", |v| {
-                    Ok(to_value(builtin_operators::key_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("keyMatch2", |v| {
-                    Ok(to_value(builtin_operators::key_match2(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("ipMatch", |v| {
-                    Ok(to_value(builtin_operators::ip_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("regexMatch", |v| {
-                    Ok(to_value(builtin_operators::regex_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                });
-
-            if self.model.data.contains_key("g"){
-                let g = &self.model.data["g"];
-                for gfunction in g{
-                    let role_manager = Arc::clone(&self.role_manager);
-                    let name = gfunction.0;
-                    expr = expr.function(name.to_string(), move|v|{
-                        let name1 = v[0].as_str().unwrap();
-                        let name2 = v[1].as_str().unwrap();
-                        let mut domain = None;
-
-                        if v.len() > 2{
-                            domain = Some(v[2].as_str().unwrap());
-                        }
-
-                        let result = role_manager.lock().unwrap().has_link(name1, name2, domain);
-                        Ok(to_value(result))
-                    });
-                }
-            }
-
-            let result = expr.exec().map_err(Error::Eval)?;
+        self.enforce_request(
+            &[("r_sub", subject), ("r_obj", object), ("r_act", action)],
+            &["p_sub", "p_obj", "p_act"],
+        )
+    }
 
-            if result == to_value(false) {
-                policy_effects.push(Effect::Indeterminate);
-                continue;
-            }
+    pub fn enforce_with_domain(&self, subject: &str, domain: &str, object: &str, action: &str) -> Result<bool, Error> {
+        self.enforce_request(
+            &[
+                ("r_sub", subject),
+                ("r_dom", domain),
+                ("r_obj", object),
+                ("r_act", action),
+            ],
+            &["p_sub", "p_dom", "p_obj", "p_act"],
+        )
+    }
 
-            // TODO(sduquette): Assuming that the effect of rules is Allow for now.
-            policy_effects.push(Effect::Allow);
-        }
+    pub fn enforce_without_users(&self, subject: &str, action: &str) -> Result<bool, Error>{
+        let expr_string = self.get_definition("m")?;
 
-        let effect_expr = &self.model.data["e"]["e"].value;
-        self.effector.merge_effects(effect_expr, policy_effects, vec![])
+        // Temporary solution to allow test_basic_model_without_users() and test_permission_api() to both work using this function
+        let matcher = if expr_string.contains("obj") { "obj" } else { "sub" };
+
+        let request_token = format!("{}{}", "r_", matcher);
+        let policy_token = format!("{}{}", "p_", matcher);
+        self.enforce_request(
+            &[(&request_token, subject), ("r_act", action)],
+            &[&policy_token, "p_act"],
+        )
     }
 
+    /// Evaluate the matcher for the `request`, given as (token, value) pairs, against the rules of `p`
+    /// whose fields are bound to the `policy_tokens` in order, and merge their effects.
+    ///
+    /// Only the rules selected by `candidate_positions` are evaluated, the others cannot match and
+    /// get the same effect as a rule the matcher is false for.
     // TODO: enforce does not handle matcherResults.
-    pub fn enforce_with_domain(&self, subject: &str, domain: &str, object: &str, action: &str) -> Result<bool, Error> {
-        let mut policy_effects: Vec<Effect> = vec![];
-
-        let expr_string = &self.model.data["m"]["m"].value;
-
-        for policy in &self.model.data["p"]["p"].policy {
-            let mut expr = Expr::new(expr_string.clone())
-                .value("r_sub", subject)
-                .value("r_dom", domain)
-                .value("r_obj", object)
-                .value("r_act", action)
-                .value("p_sub", &policy[0])
-                .value("p_dom", &policy[1])
-                .value("p_obj", &policy[2])
-                .value("p_act", &policy[3])
+    fn enforce_request(&self, request: &[(&str, &str)], policy_tokens: &[&str]) -> Result<bool, Error> {
+        let expr_string = self.get_definition("m")?;
+        let rules = self.model.get_rules("p", "p").unwrap_or_default();
+
+        let mut policy_effects: Vec<Effect> = rules.iter().map(|_| Effect::Indeterminate).collect();
+        let positions = match self.candidate_positions(request, policy_tokens) {
+            Some(positions) => positions,
+            None => (0..rules.len()).collect(),
+        };
+
+        for position in positions {
+            let policy = &rules[position];
+            let mut expr = Expr::new(expr_string);
+            for (token, value) in request {
+                expr = expr.value(*token, *value);
+            }
+            for (token, value) in policy_tokens.iter().zip(policy) {
+                expr = expr.value(*token, value);
+            }
+            expr = expr
                 .function("keyMatch", |v| {
                     Ok(to_value(builtin_operators::key_match(
                         v[0].as_str().unwrap(),
@@ -196,125 +264,121 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
                     )))
                 });
 
-            if self.model.data.contains_key("g"){
-                let g = &self.model.data["g"];
-                for gfunction in g{
-                    let role_manager = Arc::clone(&self.role_manager);
-                    let name = gfunction.0;
-                    expr = expr.function(name.to_string(), move|v|{
-                        let name1 = v[0].as_str().unwrap();
-                        let name2 = v[1].as_str().unwrap();
-                        let mut domain = None;
-
-                        if v.len() > 2{
-                            domain = Some(v[2].as_str().unwrap());
-                        }
-
-                        let result = role_manager.lock().unwrap().has_link(name1, name2, domain);
-                        Ok(to_value(result))
-                    });
-                }
+            for name in &self.model.get_ptypes("g") {
+                let role_manager = match self.role_managers.get(name) {
+                    Some(role_manager) => Arc::clone(role_manager),
+                    None => continue,
+                };
+                expr = expr.function(name.to_string(), move|v|{
+                    let name1 = v[0].as_str().unwrap();
+                    let name2 = v[1].as_str().unwrap();
+                    let fields: Vec<&str> = v[2..].iter().map(|f| f.as_str().unwrap()).collect();
+
+                    let result = role_manager.read().unwrap().has_link_with_fields(name1, name2, &fields);
+                    Ok(to_value(result))
+                });
             }
 
-            let result = expr.exec().map_err(Error::Eval)?;
+            let result = expr
+                .exec()
+                .map_err(|err| MatcherError::new(&unescape_assertion(expr_string), err))?;
 
             if result == to_value(false) {
-                policy_effects.push(Effect::Indeterminate);
                 continue;
             }
 
             // TODO(sduquette): Assuming that the effect of rules is Allow for now.
-            policy_effects.push(Effect::Allow);
+            policy_effects[position] = Effect::Allow;
         }
 
-        let effect_expr = &self.model.data["e"]["e"].value;
+        let effect_expr = self.get_definition("e")?;
         self.effector.merge_effects(effect_expr, policy_effects, vec![])
     }
 
-    // TODO: enforce does not handle matcherResults.
-    pub fn enforce_without_users(&self, subject: &str, action: &str) -> Result<bool, Error>{
-        let mut policy_effects: Vec<Effect> = vec![];
-
-        let expr_string = &self.model.data["m"]["m"].value;
-        let matcher;
-
-        // Temporary solution to allow test_basic_model_without_users() and test_permission_api() to both work using this function
-        if expr_string.contains("obj"){
-            matcher = "obj";
-        }
-        else {
-            matcher = "sub"
-        }
-
-        for policy in &self.model.data["p"]["p"].policy{
-            let mut expr = Expr::new(expr_string.clone())
-                .value(format!("{}{}", "r_", matcher), subject)
-                .value("r_act", action)
-                .value(format!("{}{}", "p_", matcher), &policy[0])
-                .value("p_act", &policy[1])
-                .function("keyMatch", |v| {
-                    Ok(to_value(builtin_operators::key_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("keyMatch2", |v| {
-                    Ok(to_value(builtin_operators::key_match2(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("ipMatch", |v| {
-                    Ok(to_value(builtin_operators::ip_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                })
-                .function("regexMatch", |v| {
-                    Ok(to_value(builtin_operators::regex_match(
-                        v[0].as_str().unwrap(),
-                        v[1].as_str().unwrap(),
-                    )))
-                });
+    /// Get the positions of the rules of `p` the `request` can match, in increasing order.
+    ///
+    /// Each predicate of the matcher on an indexed field selects the rules with the value of the
+    /// request, or with one of the roles it inherits for a role predicate, the smallest selection is
+    /// returned. Returns None if no predicate can be used, every rule must then be evaluated.
+    fn candidate_positions(&self, request: &[(&str, &str)], policy_tokens: &[&str]) -> Option<Vec<usize>> {
+        let request_value = |token: &str| request.iter().find(|(t, _)| *t == token).map(|(_, value)| *value);
+
+        let mut candidates: Option<Vec<usize>> = None;
+        for predicate in &self.predicates {
+            let field = match policy_tokens
+                .iter()
+                .position(|token| *token == predicate.policy_token())
+            {
+                Some(field) => field,
+                None => continue,
+            };
+
+            let positions = match *predicate {
+                Predicate::Equal { ref request, .. } => request_value(request)
+                    .and_then(|value| self.model.find_rule_positions("p", "p", field, value))
+                    .map(|positions| positions.to_vec()),
+                Predicate::Role {
+                    ref ptype,
+                    ref request,
+                    ref fields,
+                    ..
+                } => {
+                    let fields: Option<Vec<&str>> = fields.iter().map(|field| request_value(field)).collect();
+                    match (request_value(request), fields) {
+                        (Some(value), Some(fields)) => self.role_positions(ptype, value, &fields, field),
+                        _ => None,
+                    }
+                }
+            };
 
-            if self.model.data.contains_key("g"){
-                let g = &self.model.data["g"];
-                for gfunction in g{
-                    let role_manager = Arc::clone(&self.role_manager);
-                    let name = gfunction.0;
-                    expr = expr.function(name.to_string(), move|v|{
-                        let name1 = v[0].as_str().unwrap();
-                        let name2 = v[1].as_str().unwrap();
-                        let mut domain = None;
-
-                        if v.len() > 2{
-                            domain = Some(v[2].as_str().unwrap());
-                        }
-
-                        let result = role_manager.lock().unwrap().has_link(name1, name2, domain);
-                        Ok(to_value(result))
-                    });
+            if let Some(positions) = positions {
+                if candidates.as_ref().is_none_or(|c| positions.len() < c.len()) {
+                    candidates = Some(positions);
                 }
             }
+        }
+        candidates
+    }
 
-            let result = expr.exec().map_err(Error::Eval)?;
-
-            if result == to_value(false) {
-                policy_effects.push(Effect::Indeterminate);
-                continue;
-            }
+    /// Get the positions of the rules of `p` whose `field` is `name` or one of the roles it inherits in
+    /// the role definition `ptype`, or None if the roles cannot be listed or the field is not indexed.
+    fn role_positions(&self, ptype: &str, name: &str, fields: &[&str], field: usize) -> Option<Vec<usize>> {
+        let role_manager = self.role_managers.get(ptype)?.read().unwrap();
+        if !role_manager.can_enumerate_roles() {
+            return None;
+        }
 
-            // TODO(sduquette): Assuming that the effect of rules is Allow for now.
-            policy_effects.push(Effect::Allow);
+        let mut positions = self.model.find_rule_positions("p", "p", field, name)?.to_vec();
+        for role in role_manager.get_implicit_roles_with_fields(name, fields) {
+            positions.extend_from_slice(self.model.find_rule_positions("p", "p", field, &role)?);
         }
+        positions.sort();
+        positions.dedup();
+        Some(positions)
+    }
 
-        let effect_expr = &self.model.data["e"]["e"].value;
-        self.effector.merge_effects(effect_expr, policy_effects, vec![])
+    /// Add a matching function to the role manager of the `g` role definition.
+    pub fn add_matching_function(&self, name: &str, matching_func: MatchingFunction){
+        self.add_named_matching_function("g", name, matching_func);
     }
 
+    /// Add a matching function to the role manager of the role definition `ptype`.
+    pub fn add_named_matching_function(&self, ptype: &str, name: &str, matching_func: MatchingFunction) {
+        if let Some(role_manager) = self.role_managers.get(ptype) {
+            role_manager.write().unwrap().add_matching_function(name, matching_func);
+        }
+    }
+}
 
-    pub fn add_matching_function(&self, name: &str, matching_func: MatchingFunction){
-        self.role_manager.lock().unwrap().add_matching_function(name, matching_func);
+impl<RM: RoleManager + Send + Sync + 'static, E: Effector> Enforcer<MemoryAdapter, RM, E> {
+    /// Create an instance of an Enforcer from a `model` without a storage, see `Enforcer::new`.
+    ///
+    /// The policy starts empty and is kept in memory, it is built with the management API.
+    pub fn from_model<F>(model: Model, role_manager: F, effector: E) -> Result<Enforcer<MemoryAdapter, RM, E>, Error>
+    where
+        F: Fn() -> RM,
+    {
+        Enforcer::new(model, MemoryAdapter::new(), role_manager, effector)
     }
 }
 
@@ -324,21 +388,17 @@ mod tests {
 
     use crate::persist::file_adapter::FileAdapter;
     use std::ptr::null;
-    use crate::util::{array_2_d_equals, array_equals};
+    use crate::util::array_2_d_equals;
 
     #[test]
     fn test_match_in_memory() {
-        let mut model = Model::new();
-
-        assert_eq!(model.add_def("r", "r", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("p", "p", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("e", "e", "some(where (p.eft == allow))").unwrap(), true);
-        assert_eq!(
-            model
-                .add_def("m", "m", "(r.sub == p.sub) && (r.obj == p.obj) && (r.act == p.act)")
-                .unwrap(),
-            true
-        );
+        let model = Model::builder()
+            .request(&["sub", "obj", "act"])
+            .policy(&["sub", "obj", "act"])
+            .effect("some(where (p.eft == allow))")
+            .matcher("(r.sub == p.sub) && (r.obj == p.obj) && (r.act == p.act)")
+            .build()
+            .unwrap();
 
         let adapter = FileAdapter::new("examples/basic_policy.csv", false);
         let enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
@@ -351,20 +411,13 @@ mod tests {
 
     #[test]
     fn test_key_match_in_memory() {
-        let mut model = Model::new();
-        assert_eq!(model.add_def("r", "r", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("p", "p", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("e", "e", "some(where (p.eft == allow))").unwrap(), true);
-        assert_eq!(
-            model
-                .add_def(
-                    "m",
-                    "m",
-                    "(r.sub == p.sub) && keyMatch(r.obj, p.obj) && regexMatch(r.act, p.act)"
-                )
-                .unwrap(),
-            true
-        );
+        let model = Model::builder()
+            .request(&["sub", "obj", "act"])
+            .policy(&["sub", "obj", "act"])
+            .effect("some(where (p.eft == allow))")
+            .matcher("(r.sub == p.sub) && keyMatch(r.obj, p.obj) && regexMatch(r.act, p.act)")
+            .build()
+            .unwrap();
 
         let adapter = FileAdapter::new("examples/keymatch_policy.csv", false);
         let enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
@@ -400,20 +453,13 @@ mod tests {
 
     #[test]
     fn test_key_match_in_memory_deny() {
-        let mut model = Model::new();
-        assert_eq!(model.add_def("r", "r", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("p", "p", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("e", "e", "!some(where (p.eft == deny))").unwrap(), true);
-        assert_eq!(
-            model
-                .add_def(
-                    "m",
-                    "m",
-                    "(r.sub == p.sub) && keyMatch(r.obj, p.obj) && regexMatch(r.act, p.act)"
-                )
-                .unwrap(),
-            true
-        );
+        let model = Model::builder()
+            .request(&["sub", "obj", "act"])
+            .policy(&["sub", "obj", "act"])
+            .effect("!some(where (p.eft == deny))")
+            .matcher("(r.sub == p.sub) && keyMatch(r.obj, p.obj) && regexMatch(r.act, p.act)")
+            .build()
+            .unwrap();
 
         let adapter = FileAdapter::new("examples/keymatch_policy.csv", false);
         let enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
@@ -426,32 +472,28 @@ mod tests {
 
     #[test]
     fn test_rbac_in_memory() {
-        let mut model = Model::new();
-        assert_eq!(model.add_def("r", "r", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("p", "p", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("g", "g", "_, _").unwrap(), true);
-        assert_eq!(model.add_def("e", "e", "some(where (p.eft == allow))").unwrap(), true);
-        assert_eq!(
-            model
-                .add_def("m", "m", "g(r.sub, p.sub) && (r.obj == p.obj) && (r.act == p.act)")
-                .unwrap(),
-            true);
-
-        // TODO(sduquette): This is a temporary workaround to create an enforcer with an empty policy.
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
-
-        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]), true);
-        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]), true);
+        let model = Model::builder()
+            .request(&["sub", "obj", "act"])
+            .policy(&["sub", "obj", "act"])
+            .role("g", 2)
+            .effect("some(where (p.eft == allow))")
+            .matcher("g(r.sub, p.sub) && (r.obj == p.obj) && (r.act == p.act)")
+            .build()
+            .unwrap();
+
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
+
+        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]).unwrap(), true);
+        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]).unwrap(), true);
         assert_eq!(
-            enforcer.add_permission_for_user("data2_admin", &["data2", "read"]),
+            enforcer.add_permission_for_user("data2_admin", &["data2", "read"]).unwrap(),
             true
         );
         assert_eq!(
-            enforcer.add_permission_for_user("data2_admin", &["data2", "write"]),
+            enforcer.add_permission_for_user("data2_admin", &["data2", "write"]).unwrap(),
             true
         );
-        assert_eq!(enforcer.add_role_for_user("alice", "data2_admin"), true);
+        assert_eq!(enforcer.add_role_for_user("alice", "data2_admin").unwrap(), true);
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -478,15 +520,14 @@ mod tests {
         m = g(r.sub, p.sub) && (r.obj == p.obj) && (r.act == p.act)";
 
         let mut model = Model::from_string(text).unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
 
-        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]), true);
-        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]), true);
-        assert_eq!(enforcer.add_permission_for_user("data2_admin", &["data2", "read"]), true);
-        assert_eq!(enforcer.add_permission_for_user("data2_admin", &["data2", "write"]), true);
+        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]).unwrap(), true);
+        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]).unwrap(), true);
+        assert_eq!(enforcer.add_permission_for_user("data2_admin", &["data2", "read"]).unwrap(), true);
+        assert_eq!(enforcer.add_permission_for_user("data2_admin", &["data2", "write"]).unwrap(), true);
 
-        assert_eq!(enforcer.add_role_for_user("alice", "data2_admin"), true);
+        assert_eq!(enforcer.add_role_for_user("alice", "data2_admin").unwrap(), true);
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -500,22 +541,19 @@ mod tests {
 
     #[test]
     fn test_not_used_rbac_in_memory(){
-        let mut model = Model::new();
-        assert_eq!(model.add_def("r", "r", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("p", "p", "sub, obj, act").unwrap(), true);
-        assert_eq!(model.add_def("g", "g", "_, _").unwrap(), true);
-        assert_eq!(model.add_def("e", "e", "some(where (p.eft == allow))").unwrap(), true);
-        assert_eq!(
-            model
-                .add_def("m", "m", "g(r.sub, p.sub) && (r.obj == p.obj) && (r.act == p.act)")
-                .unwrap(),
-            true);
+        let model = Model::builder()
+            .request(&["sub", "obj", "act"])
+            .policy(&["sub", "obj", "act"])
+            .role("g", 2)
+            .effect("some(where (p.eft == allow))")
+            .matcher("g(r.sub, p.sub) && (r.obj == p.obj) && (r.act == p.act)")
+            .build()
+            .unwrap();
 
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
 
-        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]), true);
-        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]), true);
+        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]).unwrap(), true);
+        assert_eq!(enforcer.add_permission_for_user("bob", &["data2", "write"]).unwrap(), true);
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -532,10 +570,9 @@ mod tests {
     /// Test failed because Eval crates does not recognize `in` operator (jtrepanier)
     fn test_matcher_using_in_operator(){
         let mut model = Model::from_file("examples/rbac_model_matcher_using_in_op.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
 
-        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]), true);
+        assert_eq!(enforcer.add_permission_for_user("alice", &["data1", "read"]).unwrap(), true);
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data2", "read").unwrap(), true);
@@ -585,10 +622,159 @@ mod tests {
     #[test]
     fn test_role_links(){
         let mut model = Model::from_file("examples/rbac_model.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
         enforcer.build_role_links().unwrap();
 
         enforcer.enforce("user501", "data9", "read").unwrap();
     }
+
+    #[test]
+    fn test_role_manager_of_every_role_definition() {
+        let model = Model::from_file("examples/rbac_with_resource_roles_model.conf").unwrap();
+        let mut enforcer = Enforcer::from_model(model, || DefaultRoleManager::new(1), DefaultEffector::new())
+            .expect("failed to create instance of Enforcer");
+        enforcer.add_policy(&["alice", "data_group", "read"]).unwrap();
+        enforcer
+            .add_named_grouping_policy("g2", &["data1", "data_group"])
+            .unwrap();
+        enforcer.add_named_grouping_policy("g2", &["data2", "data1"]).unwrap();
+
+        // the hierarchy level given to the role managers applies to `g2` as well
+        assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
+        assert_eq!(enforcer.enforce("alice", "data2", "read").unwrap(), false);
+    }
+
+    #[test]
+    fn test_enforce_with_candidate_rules() {
+        let model = Model::from_file("examples/basic_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/basic_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        let request = [("r_sub", "bob"), ("r_obj", "data2"), ("r_act", "write")];
+        let tokens = ["p_sub", "p_obj", "p_act"];
+        assert_eq!(enforcer.candidate_positions(&request, &tokens), Some(vec![1]));
+
+        enforcer.add_policy(&["eve", "data1", "read"]).unwrap();
+        assert_eq!(enforcer.enforce("eve", "data1", "read").unwrap(), true);
+        enforcer.remove_policy(&["alice", "data1", "read"]).unwrap();
+        assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), false);
+        assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), true);
+    }
+
+    #[test]
+    fn test_candidate_rules_with_roles() {
+        let model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_domains_policy.csv", false);
+        let enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        let tokens = ["p_sub", "p_dom", "p_obj", "p_act"];
+        let request = [
+            ("r_sub", "alice"),
+            ("r_dom", "domain1"),
+            ("r_obj", "data1"),
+            ("r_act", "read"),
+        ];
+        assert_eq!(enforcer.candidate_positions(&request, &tokens), Some(vec![0, 1]));
+        let request = [
+            ("r_sub", "alice"),
+            ("r_dom", "domain2"),
+            ("r_obj", "data2"),
+            ("r_act", "read"),
+        ];
+        assert_eq!(enforcer.candidate_positions(&request, &tokens), Some(vec![]));
+
+        let model = Model::from_file("examples/rbac_with_pattern_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_pattern_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+        enforcer.add_named_matching_function(
+            "g2",
+            "keyMatch2",
+            MatchingFunction(Box::new(builtin_operators::key_match2)),
+        );
+
+        // the roles of `/book/1` are found by pattern and cannot be listed, the rules are selected by subject
+        let request = [("r_sub", "alice"), ("r_obj", "/book/1"), ("r_act", "GET")];
+        let tokens = ["p_sub", "p_obj", "p_act"];
+        assert_eq!(enforcer.candidate_positions(&request, &tokens), Some(vec![0, 1, 2]));
+        assert_eq!(enforcer.enforce("alice", "/book/1", "GET").unwrap(), true);
+    }
+
+    #[test]
+    fn test_candidate_rules_with_string_literals() {
+        let model = || {
+            Model::builder()
+                .request(&["sub", "obj", "act"])
+                .policy(&["sub", "obj", "act"])
+                .effect("some(where (p.eft == allow))")
+                .matcher(r#"r.sub == p.sub && r.obj == ")" || r.act == "(""#)
+                .build()
+                .unwrap()
+        };
+        let mut indexed = DefaultEnforcer::from_model(model()).unwrap();
+        indexed.add_policy(&["alice", "data1", "read"]).unwrap();
+        let mut full_scan = DefaultEnforcer::from_model(model()).unwrap();
+        full_scan.add_policy(&["alice", "data1", "read"]).unwrap();
+        full_scan.predicates.clear();
+
+        assert_eq!(indexed.predicates.is_empty(), true);
+        for (name, object, action) in &[("bob", "data1", "("), ("alice", ")", "read"), ("bob", ")", "read")] {
+            assert_eq!(
+                indexed.enforce(name, object, action).unwrap(),
+                full_scan.enforce(name, object, action).unwrap()
+            );
+        }
+        assert_eq!(indexed.enforce("bob", "data1", "(").unwrap(), true);
+    }
+
+    #[test]
+    fn test_candidate_rules_match_full_scan() {
+        let cases = [
+            ("examples/basic_model.conf", "examples/basic_policy.csv"),
+            ("examples/rbac_model.conf", "examples/rbac_policy.csv"),
+            (
+                "examples/rbac_with_deny_model.conf",
+                "examples/rbac_with_deny_policy.csv",
+            ),
+            (
+                "examples/rbac_with_resource_roles_model.conf",
+                "examples/rbac_with_resource_roles_policy.csv",
+            ),
+            ("examples/keymatch_model.conf", "examples/keymatch_policy.csv"),
+            ("examples/priority_model.conf", "examples/priority_policy.csv"),
+        ];
+        let names = ["alice", "bob", "cathy", "data2_admin", "data_group_admin", "eve"];
+        let objects = [
+            "data1",
+            "data2",
+            "data3",
+            "/alice_data/resource1",
+            "/bob_data/resource2",
+            "/cathy_data",
+        ];
+        let actions = ["read", "write", "GET", "POST"];
+
+        for (model_path, policy_path) in &cases {
+            let model = Model::from_file(model_path).unwrap();
+            let indexed = DefaultEnforcer::new(model, FileAdapter::new(policy_path, false)).unwrap();
+            let model = Model::from_file(model_path).unwrap();
+            let mut full_scan = DefaultEnforcer::new(model, FileAdapter::new(policy_path, false)).unwrap();
+            full_scan.predicates.clear();
+
+            for name in &names {
+                for object in &objects {
+                    for action in &actions {
+                        assert_eq!(
+                            indexed.enforce(name, object, action).unwrap(),
+                            full_scan.enforce(name, object, action).unwrap(),
+                            "{} {} {} {}",
+                            model_path,
+                            name,
+                            object,
+                            action
+                        );
+                    }
+                }
+            }
+        }
+    }
 }
@@ -1,17 +1,134 @@
 use crate::effect::Effector;
 use crate::enforcer::Enforcer;
+use crate::error::Error;
 use crate::persist::Adapter;
 use crate::rbac::RoleManager;
+use crate::util::to_strings;
 
-impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM, E> {
+impl<A: Adapter, RM: RoleManager + Send + Sync + 'static, E: Effector> Enforcer<A, RM, E> {
     /// Add a rule to the current policy.
-    pub(crate) fn add_policy_internal(&mut self, section: &str, ptype: &str, rule: &[&str]) -> bool {
+    pub(crate) fn add_policy_internal(&mut self, section: &str, ptype: &str, rule: &[&str]) -> Result<bool, Error> {
+        self.model.check_rule(section, ptype, rule, None)?;
+        if self.model.has_policy(section, ptype, rule) {
+            return Ok(false);
+        }
+
+        if self.auto_save {
+            saved(self.adapter.add_policy(section, ptype, to_strings(rule)))?;
+        }
+
         self.model.add_policy(section, ptype, rule)
     }
 
+    /// Check that `rules` match their definition, returns false if there is no rule or any of them
+    /// already exists or is repeated.
+    pub(crate) fn are_new_rules(&self, section: &str, ptype: &str, rules: &[Vec<&str>]) -> Result<bool, Error> {
+        if rules.is_empty() {
+            return Ok(false);
+        }
+
+        for (i, rule) in rules.iter().enumerate() {
+            self.model.check_rule(section, ptype, rule, None)?;
+            if self.model.has_policy(section, ptype, rule) || rules[..i].contains(rule) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Add several rules to the current policy, all or none.
+    pub(crate) fn add_policies_internal(
+        &mut self,
+        section: &str,
+        ptype: &str,
+        rules: &[Vec<&str>],
+    ) -> Result<bool, Error> {
+        if !self.are_new_rules(section, ptype, rules)? {
+            return Ok(false);
+        }
+
+        if self.auto_save {
+            let rules = rules.iter().map(|rule| to_strings(rule)).collect();
+            saved(self.adapter.add_policies(section, ptype, rules))?;
+        }
+
+        self.model.add_policies(section, ptype, rules)
+    }
+
     /// Remove a rule from the current policy
-    pub(crate) fn remove_policy_internal(&mut self, section: &str, ptype: &str, rule: &[&str]) -> bool {
-        self.model.remove_policy(section, ptype, rule)
+    pub(crate) fn remove_policy_internal(&mut self, section: &str, ptype: &str, rule: &[&str]) -> Result<bool, Error> {
+        if !self.model.has_policy(section, ptype, rule) {
+            return Ok(false);
+        }
+
+        if self.auto_save {
+            saved(self.adapter.remove_policy(section, ptype, to_strings(rule)))?;
+        }
+
+        Ok(self.model.remove_policy(section, ptype, rule))
+    }
+
+    /// Remove several rules from the current policy, all or none.
+    pub(crate) fn remove_policies_internal(
+        &mut self,
+        section: &str,
+        ptype: &str,
+        rules: &[Vec<&str>],
+    ) -> Result<bool, Error> {
+        if rules.is_empty() {
+            return Ok(false);
+        }
+
+        for (i, rule) in rules.iter().enumerate() {
+            if !self.model.has_policy(section, ptype, rule) || rules[..i].contains(rule) {
+                return Ok(false);
+            }
+        }
+
+        if self.auto_save {
+            let rules = rules.iter().map(|rule| to_strings(rule)).collect();
+            saved(self.adapter.remove_policies(section, ptype, rules))?;
+        }
+
+        Ok(self.model.remove_policies(section, ptype, rules))
+    }
+
+    /// Replace each rule of `old_rules` by the rule at the same index in `new_rules`, all or none.
+    pub(crate) fn update_policies_internal(
+        &mut self,
+        section: &str,
+        ptype: &str,
+        old_rules: &[Vec<&str>],
+        new_rules: &[Vec<&str>],
+    ) -> Result<bool, Error> {
+        if old_rules.is_empty() {
+            return Ok(false);
+        }
+
+        for rule in new_rules {
+            self.model.check_rule(section, ptype, rule, None)?;
+        }
+        if self
+            .model
+            .find_policy_updates(section, ptype, old_rules, new_rules)
+            .is_none()
+        {
+            return Ok(false);
+        }
+
+        if self.auto_save {
+            let result = if old_rules.len() == 1 {
+                self.adapter
+                    .update_policy(section, ptype, to_strings(&old_rules[0]), to_strings(&new_rules[0]))
+            } else {
+                let old_rules = old_rules.iter().map(|rule| to_strings(rule)).collect();
+                let new_rules = new_rules.iter().map(|rule| to_strings(rule)).collect();
+                self.adapter.update_policies(section, ptype, old_rules, new_rules)
+            };
+            saved(result)?;
+        }
+
+        self.model.update_policies(section, ptype, old_rules, new_rules)
     }
 
     /// Remove rules based on field filters from the current policy.
@@ -21,8 +138,27 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
         ptype: &str,
         field_index: usize,
         field_values: &[&str],
-    ) -> bool {
-        self.model
-            .remove_filtered_policy(section, ptype, field_index, field_values)
+    ) -> Result<bool, Error> {
+        if self.auto_save {
+            let field_values = to_strings(field_values);
+            let result = self
+                .adapter
+                .remove_filtered_policy(section, ptype, field_index as i32, field_values);
+            saved(result)?;
+        }
+
+        Ok(self
+            .model
+            .remove_filtered_policy(section, ptype, field_index, field_values))
     }
 }
+
+/// Check the result of saving a change to the adapter, adapters that do not implement incremental
+/// changes (e.g. the file adapter) are only written by `save_policy`.
+fn saved(result: Result<(), Error>) -> Result<(), Error> {
+    match result {
+        Ok(()) | Err(Error::NotImplemented) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
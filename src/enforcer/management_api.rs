@@ -1,9 +1,11 @@
 use crate::effect::Effector;
 use crate::enforcer::Enforcer;
+use crate::error::Error;
 use crate::persist::Adapter;
 use crate::rbac::RoleManager;
+use crate::util::to_strings;
 
-impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM, E> {
+impl<A: Adapter, RM: RoleManager + Send + Sync + 'static, E: Effector> Enforcer<A, RM, E> {
     /// Get the list of subjects that show up in the current policy.
     pub fn get_all_subjects(&self) -> Vec<String> {
         self.get_all_named_subjects("p")
@@ -112,7 +114,7 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
     ///
     /// If the rule already exists, the function returns false and the rule will not be added.
     /// Otherwise the function returns true by adding the new rule.
-    pub fn add_policy(&mut self, policy: &[&str]) -> bool {
+    pub fn add_policy(&mut self, policy: &[&str]) -> Result<bool, Error> {
         self.add_named_policy("p", policy)
     }
 
@@ -120,30 +122,97 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
     ///
     /// If the rule already exists, the function returns false and the rule will not be added.
     /// Otherwise the function returns true by adding the new rule.
-    pub fn add_named_policy(&mut self, ptype: &str, policy: &[&str]) -> bool {
+    pub fn add_named_policy(&mut self, ptype: &str, policy: &[&str]) -> Result<bool, Error> {
         self.add_policy_internal("p", ptype, policy)
     }
 
+    /// Add several authorization rules to the current policy.
+    ///
+    /// The rules are added all or none: if any of them already exists, the function returns false and
+    /// no rule is added. Otherwise the function returns true by adding all the new rules.
+    pub fn add_policies(&mut self, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.add_named_policies("p", policies)
+    }
+
+    /// Add several authorization rules to the current named policy, see `add_policies`.
+    pub fn add_named_policies(&mut self, ptype: &str, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.add_policies_internal("p", ptype, policies)
+    }
+
     /// Remove an authorization rule from the current policy.
-    pub fn remove_policy(&mut self, policy: &[&str]) -> bool {
+    pub fn remove_policy(&mut self, policy: &[&str]) -> Result<bool, Error> {
         self.remove_named_policy("p", policy)
     }
 
     /// Remove an authorization rule from the current policy, field filters can be specified.
-    pub fn remove_filtered_policy(&mut self, field_index: usize, field_values: &[&str]) -> bool {
+    pub fn remove_filtered_policy(&mut self, field_index: usize, field_values: &[&str]) -> Result<bool, Error> {
         self.remove_filtered_named_policy("p", field_index, field_values)
     }
 
     /// Remove an authorization rule from the current named policy.
-    pub fn remove_named_policy(&mut self, ptype: &str, policy: &[&str]) -> bool {
+    pub fn remove_named_policy(&mut self, ptype: &str, policy: &[&str]) -> Result<bool, Error> {
         self.remove_policy_internal("p", ptype, policy)
     }
 
+    /// Remove several authorization rules from the current policy.
+    ///
+    /// The rules are removed all or none: if any of them does not exist, the function returns false and
+    /// no rule is removed.
+    pub fn remove_policies(&mut self, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.remove_named_policies("p", policies)
+    }
+
+    /// Remove several authorization rules from the current named policy, see `remove_policies`.
+    pub fn remove_named_policies(&mut self, ptype: &str, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.remove_policies_internal("p", ptype, policies)
+    }
+
     /// Remove an authorization rule from the current named policy, field filters can be specified.
-    pub fn remove_filtered_named_policy(&mut self, ptype: &str, field_index: usize, field_values: &[&str]) -> bool {
+    pub fn remove_filtered_named_policy(
+        &mut self,
+        ptype: &str,
+        field_index: usize,
+        field_values: &[&str],
+    ) -> Result<bool, Error> {
         self.remove_filtered_policy_internal("p", ptype, field_index, field_values)
     }
 
+    /// Replace the authorization rule `old_policy` by `new_policy` in the current policy.
+    ///
+    /// The new rule takes the position of the old one. If the old rule does not exist or the new rule
+    /// already exists, the function returns false and the policy is left unchanged.
+    pub fn update_policy(&mut self, old_policy: &[&str], new_policy: &[&str]) -> Result<bool, Error> {
+        self.update_named_policy("p", old_policy, new_policy)
+    }
+
+    /// Replace the authorization rule `old_policy` by `new_policy` in the current named policy, see
+    /// `update_policy`.
+    pub fn update_named_policy(
+        &mut self,
+        ptype: &str,
+        old_policy: &[&str],
+        new_policy: &[&str],
+    ) -> Result<bool, Error> {
+        self.update_policies_internal("p", ptype, &[old_policy.to_vec()], &[new_policy.to_vec()])
+    }
+
+    /// Replace each authorization rule of `old_policies` by the rule at the same index in `new_policies`.
+    ///
+    /// The rules are replaced all or none, see `update_policy`.
+    pub fn update_policies(&mut self, old_policies: &[Vec<&str>], new_policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.update_named_policies("p", old_policies, new_policies)
+    }
+
+    /// Replace several authorization rules of the current named policy, see `update_policies`.
+    pub fn update_named_policies(
+        &mut self,
+        ptype: &str,
+        old_policies: &[Vec<&str>],
+        new_policies: &[Vec<&str>],
+    ) -> Result<bool, Error> {
+        self.update_policies_internal("p", ptype, old_policies, new_policies)
+    }
+
     /// Determine whether a role inheritance rule exists.
     pub fn has_grouping_policy(&self, policy: &[&str]) -> bool {
         self.has_named_grouping_policy("g", policy)
@@ -158,43 +227,99 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
     ///
     /// If the rule already exists, the function returns false and the rule will not be added.
     /// Otherwise the function returns true by adding the new rule.
-    pub fn add_grouping_policy(&mut self, policy: &[&str]) -> bool {
+    pub fn add_grouping_policy(&mut self, policy: &[&str]) -> Result<bool, Error> {
         self.add_named_grouping_policy("g", policy)
     }
 
     /// Add a named role inheritance rule to the current policy.
     ///
     /// If the rule already exists, the function returns false and the rule will not be added.
-    /// Otherwise the function returns true by adding the new rule.
-    pub fn add_named_grouping_policy(&mut self, ptype: &str, policy: &[&str]) -> bool {
-        let rule_added = self.add_policy_internal("g", ptype, policy);
+    /// Otherwise the function returns true by adding the new rule. If the rule cannot be added to the
+    /// role manager (e.g. it makes a role inherit from itself), an error is returned and the rule is
+    /// neither saved nor kept in the policy.
+    pub fn add_named_grouping_policy(&mut self, ptype: &str, policy: &[&str]) -> Result<bool, Error> {
+        self.add_named_grouping_policies(ptype, &[policy.to_vec()])
+    }
+
+    /// Add several role inheritance rules to the current policy.
+    ///
+    /// The rules are added all or none: if any of them already exists, the function returns false and
+    /// no rule is added. Otherwise the function returns true by adding all the new rules.
+    pub fn add_grouping_policies(&mut self, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.add_named_grouping_policies("g", policies)
+    }
 
-        if rule_added && self.auto_build_role_links {
-            self.build_role_links().expect("build_role_links failed");
+    /// Add several named role inheritance rules to the current policy, see `add_grouping_policies`.
+    pub fn add_named_grouping_policies(&mut self, ptype: &str, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        if !self.are_new_rules("g", ptype, policies)? {
+            return Ok(false);
         }
 
-        rule_added
+        // link the roles first so that the rules the role manager rejects, e.g. because they make a
+        // cycle, are neither saved to the adapter nor added to the model
+        let rules: Vec<Vec<String>> = policies.iter().map(|p| to_strings(p)).collect();
+        if self.auto_build_role_links {
+            for (i, rule) in rules.iter().enumerate() {
+                if let Err(err) = self.add_role_links(ptype, std::slice::from_ref(rule)) {
+                    self.delete_role_links(ptype, &rules[..i])?;
+                    return Err(err);
+                }
+            }
+        }
+
+        match self.add_policies_internal("g", ptype, policies) {
+            Err(err) if self.auto_build_role_links => {
+                self.delete_role_links(ptype, &rules)?;
+                Err(err)
+            }
+            result => result,
+        }
     }
 
     /// Remove a role inheritance rule from the current policy.
-    pub fn remove_grouping_policy(&mut self, policy: &[&str]) -> bool {
+    pub fn remove_grouping_policy(&mut self, policy: &[&str]) -> Result<bool, Error> {
         self.remove_named_grouping_policy("g", policy)
     }
 
     /// Remove a role inheritance rule from the current policy, field filters can be specified.
-    pub fn remove_filtered_grouping_policy(&mut self, field_index: usize, field_values: &[&str]) -> bool {
+    pub fn remove_filtered_grouping_policy(
+        &mut self,
+        field_index: usize,
+        field_values: &[&str],
+    ) -> Result<bool, Error> {
         self.remove_filtered_named_grouping_policy("g", field_index, field_values)
     }
 
     /// Remove a role inheritance rule from the current policy.
-    pub fn remove_named_grouping_policy(&mut self, ptype: &str, policy: &[&str]) -> bool {
-        let rule_removed = self.remove_policy_internal("g", ptype, policy);
+    pub fn remove_named_grouping_policy(&mut self, ptype: &str, policy: &[&str]) -> Result<bool, Error> {
+        let rule_removed = self.remove_policy_internal("g", ptype, policy)?;
 
         if rule_removed && self.auto_build_role_links {
-            self.build_role_links().expect("build_role_links failed");
+            let rule = to_strings(policy);
+            self.delete_role_links(ptype, &[rule])?;
         }
 
-        rule_removed
+        Ok(rule_removed)
+    }
+
+    /// Remove several role inheritance rules from the current policy.
+    ///
+    /// The rules are removed all or none: if any of them does not exist, the function returns false and
+    /// no rule is removed.
+    pub fn remove_grouping_policies(&mut self, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        self.remove_named_grouping_policies("g", policies)
+    }
+
+    /// Remove several named role inheritance rules from the current policy, see `remove_grouping_policies`.
+    pub fn remove_named_grouping_policies(&mut self, ptype: &str, policies: &[Vec<&str>]) -> Result<bool, Error> {
+        let rules_removed = self.remove_policies_internal("g", ptype, policies)?;
+
+        if rules_removed && self.auto_build_role_links {
+            let rules: Vec<Vec<String>> = policies.iter().map(|p| to_strings(p)).collect();
+            self.delete_role_links(ptype, &rules)?;
+        }
+
+        Ok(rules_removed)
     }
 
     /// Remove a role inheritance rule from the current named policy, field filters can be specified.
@@ -203,23 +328,27 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
         ptype: &str,
         field_index: usize,
         field_values: &[&str],
-    ) -> bool {
-        let result = self.remove_filtered_policy_internal("g", ptype, field_index, field_values);
+    ) -> Result<bool, Error> {
+        let rules = self
+            .model
+            .get_filtered_policy("g", ptype, field_index, field_values)
+            .unwrap_or_default();
+        let result = self.remove_filtered_policy_internal("g", ptype, field_index, field_values)?;
 
-        if self.auto_build_role_links {
-            self.build_role_links().expect("build_role_links failed");
+        if result && self.auto_build_role_links {
+            self.delete_role_links(ptype, &rules)?;
         }
 
-        result
+        Ok(result)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::enforcer::DefaultEnforcer;
+    use crate::error::{Error, RoleManagerError};
     use crate::model::Model;
     use crate::persist::file_adapter::FileAdapter;
-    use crate::util::array_equals;
 
     #[test]
     fn test_get_policy_api() {
@@ -324,12 +453,12 @@ mod tests {
             ]
         );
 
-        assert_eq!(enforcer.remove_policy(&["alice", "data1", "read"]), true);
-        assert_eq!(enforcer.remove_policy(&["bob", "data2", "write"]), true);
-        assert_eq!(enforcer.add_policy(&["eve", "data3", "read"]), true);
+        assert_eq!(enforcer.remove_policy(&["alice", "data1", "read"]).unwrap(), true);
+        assert_eq!(enforcer.remove_policy(&["bob", "data2", "write"]).unwrap(), true);
+        assert_eq!(enforcer.add_policy(&["eve", "data3", "read"]).unwrap(), true);
 
-        assert_eq!(enforcer.remove_named_policy("p", &["eve", "data3", "read"]), true);
-        assert_eq!(enforcer.add_named_policy("p", &["eve", "data3", "read"]), true);
+        assert_eq!(enforcer.remove_named_policy("p", &["eve", "data3", "read"]).unwrap(), true);
+        assert_eq!(enforcer.add_named_policy("p", &["eve", "data3", "read"]).unwrap(), true);
 
         assert_eq!(
             enforcer.get_policy(),
@@ -340,7 +469,7 @@ mod tests {
             ]
         );
 
-        assert_eq!(enforcer.remove_filtered_policy(1, &["data2"]), true);
+        assert_eq!(enforcer.remove_filtered_policy(1, &["data2"]).unwrap(), true);
         assert_eq!(enforcer.get_policy(), [["eve", "data3", "read"]]);
     }
 
@@ -355,9 +484,9 @@ mod tests {
         assert_eq!(enforcer.get_roles_for_user("eve", None), Vec::<String>::new());
         assert_eq!(enforcer.get_roles_for_user("non_exist", None), Vec::<String>::new());
 
-        enforcer.remove_grouping_policy(&["alice", "data2_admin"]);
-        enforcer.add_grouping_policy(&["bob", "data1_admin"]);
-        enforcer.add_grouping_policy(&["eve", "data3_admin"]);
+        enforcer.remove_grouping_policy(&["alice", "data2_admin"]).unwrap();
+        enforcer.add_grouping_policy(&["bob", "data1_admin"]).unwrap();
+        enforcer.add_grouping_policy(&["eve", "data3_admin"]).unwrap();
 
         assert_eq!(enforcer.get_roles_for_user("alice", None), Vec::<String>::new());
         assert_eq!(enforcer.get_roles_for_user("bob", None), ["data1_admin"]);
@@ -368,7 +497,7 @@ mod tests {
         assert_eq!(enforcer.get_users_for_role("data2_admin", None), Vec::<String>::new());
         assert_eq!(enforcer.get_users_for_role("data3_admin", None), ["eve"]);
 
-        assert_eq!(enforcer.remove_filtered_grouping_policy(0, &["bob"]), true);
+        assert_eq!(enforcer.remove_filtered_grouping_policy(0, &["bob"]).unwrap(), true);
 
         assert_eq!(enforcer.get_roles_for_user("alice", None), Vec::<String>::new());
         assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
@@ -382,8 +511,225 @@ mod tests {
 
 
 
+    #[test]
+    fn test_incremental_role_links() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        // both rules imply the same link, the values beyond the role definition are custom data
+        assert_eq!(enforcer.add_grouping_policy(&["bob", "data2_admin", "custom"]).unwrap(), true);
+        assert_eq!(enforcer.add_grouping_policy(&["bob", "data2_admin"]).unwrap(), true);
+        assert_eq!(enforcer.remove_grouping_policy(&["bob", "data2_admin"]).unwrap(), true);
+        assert_eq!(enforcer.get_roles_for_user("bob", None), ["data2_admin"]);
+        assert_eq!(enforcer.enforce("bob", "data2", "read").unwrap(), true);
+
+        assert_eq!(enforcer.add_grouping_policy(&["eve", "data1_admin"]).unwrap(), true);
+        assert_eq!(enforcer.remove_filtered_grouping_policy(1, &["data2_admin"]).unwrap(), true);
+        assert_eq!(enforcer.get_roles_for_user("alice", None), Vec::<String>::new());
+        assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
+        assert_eq!(enforcer.get_roles_for_user("eve", None), ["data1_admin"]);
+    }
+
+    #[test]
+    fn test_policy_errors() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        assert!(matches!(enforcer.add_policy(&["eve", "data3"]), Err(Error::Policy(_))));
+        assert!(matches!(enforcer.add_named_policy("p2", &["eve", "data3", "read"]), Err(Error::Policy(_))));
+        assert!(matches!(
+            enforcer.update_policy(&["alice", "data1", "read"], &["alice", "data1", "read", "allow"]),
+            Err(Error::Policy(_))
+        ));
+        assert_eq!(enforcer.has_policy(&["alice", "data1", "read"]), true);
+        assert_eq!(enforcer.add_policy(&["eve", "data3", "read"]).unwrap(), true);
+    }
+
+    #[test]
+    fn test_grouping_policy_errors() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        assert!(matches!(
+            enforcer.add_grouping_policy(&["data2_admin", "alice"]),
+            Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))
+        ));
+        assert!(matches!(enforcer.add_grouping_policy(&["alice"]), Err(Error::Policy(_))));
+        assert!(matches!(
+            enforcer.add_grouping_policies(&[vec!["bob", "data1_admin"], vec!["data2_admin", "alice"]]),
+            Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))
+        ));
+        assert_eq!(enforcer.get_grouping_policy(), [["alice", "data2_admin"]]);
+        assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
+        assert_eq!(enforcer.add_grouping_policy(&["bob", "data2_admin"]).unwrap(), true);
+        assert_eq!(enforcer.add_grouping_policy(&["bob", "data2_admin"]).unwrap(), false);
+    }
+
+    #[test]
+    fn test_rejected_grouping_policy_is_not_saved() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let mut enforcer = DefaultEnforcer::from_model(model).expect("failed to create instance of Enforcer");
+        assert_eq!(enforcer.add_grouping_policy(&["alice", "data2_admin"]).unwrap(), true);
+
+        assert!(matches!(
+            enforcer.add_grouping_policy(&["data2_admin", "alice"]),
+            Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))
+        ));
+        assert!(matches!(
+            enforcer.add_grouping_policies(&[vec!["bob", "data2_admin"], vec!["data2_admin", "alice"]]),
+            Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))
+        ));
+        assert_eq!(enforcer.adapter.get_policy("g"), [["alice", "data2_admin"]]);
+        assert_eq!(enforcer.get_grouping_policy(), [["alice", "data2_admin"]]);
+        assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
+
+        // the stored policy can be loaded again
+        enforcer.load_policy().unwrap();
+        assert_eq!(enforcer.get_roles_for_user("alice", None), ["data2_admin"]);
+    }
+
+    #[test]
+    fn test_batch_policy_api() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        // alice's rule already exists, so nothing is added
+        assert_eq!(
+            enforcer.add_policies(&[vec!["eve", "data3", "read"], vec!["alice", "data1", "read"]]).unwrap(),
+            false
+        );
+        assert_eq!(enforcer.has_policy(&["eve", "data3", "read"]), false);
+
+        assert_eq!(
+            enforcer.add_policies(&[vec!["eve", "data3", "read"], vec!["eve", "data3", "write"]]).unwrap(),
+            true
+        );
+        assert_eq!(enforcer.has_policy(&["eve", "data3", "write"]), true);
+
+        // eve has no right to write data1, so nothing is removed
+        assert_eq!(
+            enforcer.remove_policies(&[vec!["eve", "data3", "read"], vec!["eve", "data1", "write"]]).unwrap(),
+            false
+        );
+        assert_eq!(enforcer.has_policy(&["eve", "data3", "read"]), true);
+
+        assert_eq!(
+            enforcer.remove_policies(&[vec!["eve", "data3", "read"], vec!["eve", "data3", "write"]]).unwrap(),
+            true
+        );
+        assert_eq!(
+            enforcer.get_policy(),
+            [
+                ["alice", "data1", "read"],
+                ["bob", "data2", "write"],
+                ["data2_admin", "data2", "read"],
+                ["data2_admin", "data2", "write"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_policy_api() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        assert_eq!(
+            enforcer.update_policy(&["bob", "data2", "write"], &["bob", "data2", "read"]).unwrap(),
+            true
+        );
+        assert_eq!(
+            enforcer.get_policy(),
+            [
+                ["alice", "data1", "read"],
+                ["bob", "data2", "read"],
+                ["data2_admin", "data2", "read"],
+                ["data2_admin", "data2", "write"]
+            ]
+        );
+        assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), false);
+        assert_eq!(enforcer.enforce("bob", "data2", "read").unwrap(), true);
+
+        // the old rule does not exist
+        assert_eq!(
+            enforcer.update_policy(&["bob", "data2", "write"], &["bob", "data3", "write"]).unwrap(),
+            false
+        );
+        // the new rule already exists
+        assert_eq!(
+            enforcer.update_policy(&["bob", "data2", "read"], &["alice", "data1", "read"]).unwrap(),
+            false
+        );
+
+        // one of the old rules does not exist, so nothing is replaced
+        assert_eq!(
+            enforcer.update_policies(
+                &[vec!["alice", "data1", "read"], vec!["eve", "data3", "read"]],
+                &[vec!["alice", "data1", "write"], vec!["eve", "data3", "write"]]
+            ).unwrap(),
+            false
+        );
+        assert_eq!(enforcer.has_policy(&["alice", "data1", "read"]), true);
+
+        // rules can be swapped
+        assert_eq!(
+            enforcer.update_policies(
+                &[vec!["data2_admin", "data2", "read"], vec!["data2_admin", "data2", "write"]],
+                &[vec!["data2_admin", "data2", "write"], vec!["data2_admin", "data2", "read"]]
+            ).unwrap(),
+            true
+        );
+        assert_eq!(
+            enforcer.get_policy(),
+            [
+                ["alice", "data1", "read"],
+                ["bob", "data2", "read"],
+                ["data2_admin", "data2", "write"],
+                ["data2_admin", "data2", "read"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_grouping_policy_api() {
+        let model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).expect("failed to create instance of Enforcer");
+
+        assert_eq!(
+            enforcer.add_grouping_policies(&[vec!["bob", "data2_admin"], vec!["alice", "data2_admin"]]).unwrap(),
+            false
+        );
+        assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
+
+        assert_eq!(
+            enforcer.add_grouping_policies(&[vec!["bob", "data2_admin"], vec!["eve", "data2_admin"]]).unwrap(),
+            true
+        );
+        assert_eq!(enforcer.get_roles_for_user("bob", None), ["data2_admin"]);
+        assert_eq!(enforcer.enforce("eve", "data2", "read").unwrap(), true);
+
+        assert_eq!(
+            enforcer.remove_grouping_policies(&[vec!["bob", "data2_admin"], vec!["eve", "data1_admin"]]).unwrap(),
+            false
+        );
+        assert_eq!(enforcer.get_roles_for_user("bob", None), ["data2_admin"]);
+
+        assert_eq!(
+            enforcer.remove_grouping_policies(&[vec!["bob", "data2_admin"], vec!["eve", "data2_admin"]]).unwrap(),
+            true
+        );
+        assert_eq!(enforcer.get_roles_for_user("bob", None), Vec::<String>::new());
+        assert_eq!(enforcer.get_roles_for_user("alice", None), ["data2_admin"]);
+        assert_eq!(enforcer.enforce("eve", "data2", "read").unwrap(), false);
+    }
+
     fn test_string_list(my_res: Vec<String>, res: Vec<String>) -> bool{
-        if !array_equals(&res, &my_res){
+        if res != my_res{
             return false;
         }
         return true;
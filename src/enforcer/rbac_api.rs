@@ -1,75 +1,183 @@
 use crate::effect::Effector;
 use crate::enforcer::Enforcer;
+use crate::error::Error;
 use crate::persist::Adapter;
-use crate::rbac::RoleManager;
-use std::collections::HashMap;
+use crate::rbac::{RoleGraph, RoleManager};
 
-impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM, E> {
+impl<A: Adapter, RM: RoleManager + Send + Sync + 'static, E: Effector> Enforcer<A, RM, E> {
     /// Get the list of roles for `user`.
     pub fn get_roles_for_user(&self, user: &str, domain: Option<&str>) -> Vec<String> {
-        self.role_manager.lock().unwrap().get_roles(user, domain)
+        self.get_named_roles_for_user("g", user, domain)
+    }
+
+    /// Get the list of roles for `user` in the role definition `ptype`.
+    pub fn get_named_roles_for_user(&self, ptype: &str, user: &str, domain: Option<&str>) -> Vec<String> {
+        match self.get_role_manager(ptype) {
+            Some(role_manager) => role_manager.read().unwrap().get_roles(user, domain),
+            None => Vec::new(),
+        }
     }
 
     /// Get the list users that have the speficied `role`.
     pub fn get_users_for_role(&self, role: &str, domain: Option<&str>) -> Vec<String> {
-        self.role_manager.lock().unwrap().get_users(role, domain)
+        self.get_named_users_for_role("g", role, domain)
+    }
+
+    /// Get the list users that have the speficied `role` in the role definition `ptype`.
+    pub fn get_named_users_for_role(&self, ptype: &str, role: &str, domain: Option<&str>) -> Vec<String> {
+        match self.get_role_manager(ptype) {
+            Some(role_manager) => role_manager.read().unwrap().get_users(role, domain),
+            None => Vec::new(),
+        }
     }
 
     /// Returns true if `user` has the specified `role`.
     pub fn has_role_for_user(&self, user: &str, role: &str, domain: Option<&str>) -> bool {
-        let roles = self.get_roles_for_user(user, domain);
+        self.has_named_role_for_user("g", user, role, domain)
+    }
+
+    /// Returns true if `user` has the specified `role` in the role definition `ptype`.
+    pub fn has_named_role_for_user(&self, ptype: &str, user: &str, role: &str, domain: Option<&str>) -> bool {
+        let roles = self.get_named_roles_for_user(ptype, user, domain);
         roles.iter().any(|r| r == role)
     }
 
     /// Add a `role` for a `user`.
-    pub fn add_role_for_user(&mut self, user: &str, role: &str) -> bool {
+    pub fn add_role_for_user(&mut self, user: &str, role: &str) -> Result<bool, Error> {
+        self.add_named_role_for_user("g", user, role)
+    }
+
+    /// Add a `role` for a `user` in the role definition `ptype`.
+    pub fn add_named_role_for_user(&mut self, ptype: &str, user: &str, role: &str) -> Result<bool, Error> {
         let policy = [user, role];
-        self.add_grouping_policy(&policy)
+        self.add_named_grouping_policy(ptype, &policy)
     }
 
     /// Delete a `role` for a `user`.
-    pub fn delete_role_for_user(&mut self, user: &str, role: &str) -> bool {
+    pub fn delete_role_for_user(&mut self, user: &str, role: &str) -> Result<bool, Error> {
+        self.delete_named_role_for_user("g", user, role)
+    }
+
+    /// Delete a `role` for a `user` in the role definition `ptype`.
+    pub fn delete_named_role_for_user(&mut self, ptype: &str, user: &str, role: &str) -> Result<bool, Error> {
         let policy = [user, role];
-        self.remove_grouping_policy(&policy)
+        self.remove_named_grouping_policy(ptype, &policy)
+    }
+
+    /// Get the list of roles for `user` in `domain`.
+    pub fn get_roles_for_user_in_domain(&self, user: &str, domain: &str) -> Vec<String> {
+        self.get_roles_for_user(user, Some(domain))
+    }
+
+    /// Get the list of users that have the specified `role` in `domain`.
+    pub fn get_users_for_role_in_domain(&self, role: &str, domain: &str) -> Vec<String> {
+        self.get_users_for_role(role, Some(domain))
     }
 
-    /// Delete a `Role`
-    pub fn delete_role(&mut self, role: &str){
-        let users = self.get_users_for_role(role, None);
-        for user in users{
-            self.delete_role_for_user(&user, role);
+    /// Add a `role` for a `user` in `domain`.
+    ///
+    /// Returns false if the user already has the role in this domain.
+    pub fn add_role_for_user_in_domain(&mut self, user: &str, role: &str, domain: &str) -> Result<bool, Error> {
+        self.add_grouping_policy(&[user, role, domain])
+    }
+
+    /// Delete a `role` for a `user` in `domain`.
+    ///
+    /// Returns false if the user does not have the role in this domain.
+    pub fn delete_role_for_user_in_domain(&mut self, user: &str, role: &str, domain: &str) -> Result<bool, Error> {
+        self.remove_grouping_policy(&[user, role, domain])
+    }
+
+    /// Delete all the roles of a `user` in `domain`.
+    ///
+    /// Returns false if the user does not have any roles in this domain.
+    pub fn delete_roles_for_user_in_domain(&mut self, user: &str, domain: &str) -> Result<bool, Error> {
+        if self.get_filtered_grouping_policy(0, &[user, "", domain]).is_empty() {
+            return Ok(false);
         }
+        self.remove_filtered_grouping_policy(0, &[user, "", domain])
+    }
+
+    /// Get the list of domains that show up in the role inheritance rules.
+    pub fn get_all_domains(&self) -> Vec<String> {
+        self.model.get_values_for_field_in_policy_all_types("g", 2)
+    }
 
-        self.remove_policy(&[role]);
+    /// Get the list of domains in which `user` has a role.
+    pub fn get_domains_for_user(&self, user: &str) -> Vec<String> {
+        let mut domains: Vec<String> = self
+            .get_filtered_grouping_policy(0, &[user])
+            .into_iter()
+            .filter_map(|rule| rule.get(2).cloned())
+            .collect();
+        domains.sort();
+        domains.dedup();
+        domains
     }
 
-    /// Delete a User
+    /// Delete a `role`.
     ///
-    /// Returns false if user does not exist
-    pub fn delete_user(&mut self, user: &str) -> bool{
-        return self.delete_roles_for_user(user);
+    /// Removes every policy rule where the role is the subject and every role inheritance rule
+    /// where it appears as a user or a role. Returns false if nothing was removed.
+    pub fn delete_role(&mut self, role: &str) -> Result<bool, Error> {
+        self.delete_subject(role)
+    }
+
+    /// Delete a `user`.
+    ///
+    /// Removes every policy rule where the user is the subject and every role inheritance rule
+    /// where it appears as a user or a role. Returns false if user does not exist.
+    pub fn delete_user(&mut self, user: &str) -> Result<bool, Error> {
+        self.delete_subject(user)
+    }
+
+    fn delete_subject(&mut self, name: &str) -> Result<bool, Error> {
+        let mut removed = false;
+
+        for ptype in self.model.get_ptypes("g") {
+            removed |= self.remove_filtered_named_grouping_policy(&ptype, 0, &[name])?;
+            removed |= self.remove_filtered_named_grouping_policy(&ptype, 1, &[name])?;
+        }
+        for ptype in self.model.get_ptypes("p") {
+            removed |= self.remove_filtered_named_policy(&ptype, 0, &[name])?;
+        }
+
+        Ok(removed)
     }
 
     /// Delete all `roles` for a `user`
     ///
     /// Returns false if user does not have any roles.
-    pub fn delete_roles_for_user(&mut self, user: &str) -> bool{
+    pub fn delete_roles_for_user(&mut self, user: &str) -> Result<bool, Error> {
         let roles = self.get_roles_for_user(user, None);
 
         if roles.len() == 0{
-            return false;
+            return Ok(false);
         }
 
         for role in roles{
-            self.delete_role_for_user(user, &role);
+            self.delete_role_for_user(user, &role)?;
+        }
+        Ok(true)
+    }
+
+    /// Get the roles and inheritance links of the `g` role definition.
+    pub fn get_role_graph(&self) -> RoleGraph {
+        self.get_named_role_graph("g")
+    }
+
+    /// Get the roles and inheritance links of the role definition `ptype`.
+    pub fn get_named_role_graph(&self, ptype: &str) -> RoleGraph {
+        match self.get_role_manager(ptype) {
+            Some(role_manager) => role_manager.read().unwrap().get_role_graph(),
+            None => RoleGraph::new(),
         }
-        return true;
     }
 
     /// Adds a permission for a `user` or `role`.
     ///
     /// Returns false if the user or role already has the permission.
-    pub fn add_permission_for_user(&mut self, user: &str, permission: &[&str]) -> bool{
+    pub fn add_permission_for_user(&mut self, user: &str, permission: &[&str]) -> Result<bool, Error> {
         let mut params = vec![user];
         params.extend(permission);
         self.add_policy(&params)
@@ -79,23 +187,46 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
         return self.get_filtered_policy(0, &[user]);
     }
 
-    //TODO (jtrepanier) Assuming we are checking for one permission
+    /// Get the permissions of a `user` or `role` in `domain`.
+    pub fn get_permissions_for_user_in_domain(&self, user: &str, domain: &str) -> Vec<Vec<String>> {
+        self.get_filtered_policy(0, &[user, domain])
+    }
+
+    /// Determines whether a `user` or `role` has a permission.
+    ///
+    /// `permission` holds every field following the subject, e.g. the object, the action and the
+    /// effect of `p = sub, obj, act, eft`, the rule must match it exactly.
     pub fn has_permission_for_user(&self, user: &str, permission: &[&str]) -> bool{
-        return self.has_policy(&vec![user, permission[0]]);
+        let mut params = vec![user];
+        params.extend(permission);
+        self.model.has_policy("p", "p", &params)
     }
 
-    pub fn delete_permission(&mut self, permission: Vec<&str>) -> bool{
+    /// Determines whether a `user` has a permission, directly or through its roles.
+    pub fn has_implicit_permission_for_user(&self, user: &str, permission: &[&str]) -> bool {
+        let mut roles = self.get_implicit_roles_for_user(user, None);
+        roles.insert(0, user.to_string());
+        roles.iter().any(|role| self.has_permission_for_user(role, permission))
+    }
+
+    /// Deletes a permission for every user and role.
+    ///
+    /// Returns false if no rule has the permission.
+    pub fn delete_permission(&mut self, permission: Vec<&str>) -> Result<bool, Error> {
         return self.remove_filtered_policy(1, &permission);
     }
 
-    //TODO (jtrepanier) Assuming we are deleting only one permission
-    pub fn delete_permission_for_user(&mut self, user: &str, permission: &[&str]) -> bool{
+    /// Deletes a permission for a `user` or `role`.
+    ///
+    /// The rule with exactly this permission is removed, see `has_permission_for_user`.
+    /// Returns false if the user or role does not have the permission.
+    pub fn delete_permission_for_user(&mut self, user: &str, permission: &[&str]) -> Result<bool, Error> {
         let mut params = vec![user];
         params.extend(permission);
-        return self.remove_policy(&params);
+        self.remove_policy(&params)
     }
 
-    pub fn delete_permissions_for_user(&mut self, user: &[&str]) -> bool{
+    pub fn delete_permissions_for_user(&mut self, user: &[&str]) -> Result<bool, Error> {
         return self.remove_filtered_policy(0, user)
     }
 
@@ -107,41 +238,118 @@ impl<A: Adapter, RM: RoleManager + Send + 'static, E: Effector> Enforcer<A, RM,
     ///
     /// get_roles_for_user("alice") can only get: ["role:admin"].
     /// but get_implicit_roles_for_user("alice") will get: ["role:admin", "role:user"].
-    pub fn get_implicit_roles_for_user(&self, name: &str) -> Vec<String>{
-        let mut res: Vec<String> = Vec::new();
-        let mut role_set: HashMap<String, bool> = HashMap::new();
-        role_set.insert(name.to_string(), true);
-
-        let mut q: Vec<String> = Vec::new();
-        q.push(name.to_string());
-
-        while q.len() > 0 {
-            let name = q[0].clone();
-            q = q.clone()[1 ..].to_vec();
-
-            let roles:Vec<String> = self.get_roles_for_user(&name, None).clone();
-            for i in 0..roles.len(){
-                match role_set.get(&roles[i]){
-                    Some(r) => (),
-                    None => {
-                        res.push(roles[i].clone());
-                        q.push(roles[i].clone());
-                        role_set.insert(roles[i].clone(), true);
-                    }
+    pub fn get_implicit_roles_for_user(&self, name: &str, domain: Option<&str>) -> Vec<String>{
+        match self.get_role_manager("g") {
+            Some(role_manager) => role_manager.read().unwrap().get_implicit_roles(name, domain),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gets implicit users that have a role.
+    /// Compared to get_users_for_role(), this function retrieves indirect users besides direct users.
+    /// For example:
+    /// g, alice, role:admin
+    /// g, role:admin, role:user
+    ///
+    /// get_users_for_role("role:user") can only get: ["role:admin"].
+    /// but get_implicit_users_for_role("role:user") will get: ["role:admin", "alice"].
+    pub fn get_implicit_users_for_role(&self, role: &str, domain: Option<&str>) -> Vec<String> {
+        match self.get_role_manager("g") {
+            Some(role_manager) => role_manager.read().unwrap().get_implicit_users(role, domain),
+            None => Vec::new(),
+        }
+    }
+
+    /// Gets the users that have a permission, directly or through their roles.
+    ///
+    /// `permission` holds the fields following the subject in the policy, e.g. `&["data1", "read"]`.
+    /// Roles are not included in the result, nor the users a matching `deny` rule applies to.
+    pub fn get_implicit_users_for_permission(&self, permission: &[&str]) -> Vec<String> {
+        let roles = self.get_all_roles();
+        self.get_implicit_users_for_fields(permission, &roles, None)
+    }
+
+    /// Gets the users that have a permission in `domain`, directly or through their roles.
+    ///
+    /// The domain is expected to be the field following the subject in the policy.
+    pub fn get_implicit_users_for_permission_in_domain(&self, permission: &[&str], domain: &str) -> Vec<String> {
+        let mut field_values = vec![domain];
+        field_values.extend(permission);
+        let roles: Vec<String> = self
+            .get_filtered_grouping_policy(2, &[domain])
+            .into_iter()
+            .filter_map(|rule| rule.get(1).cloned())
+            .collect();
+        self.get_implicit_users_for_fields(&field_values, &roles, Some(domain))
+    }
+
+    /// Gets the users of the rules matching `field_values` from the field following the subject, less
+    /// the users of the matching rules whose effect is `deny`.
+    fn get_implicit_users_for_fields(
+        &self,
+        field_values: &[&str],
+        roles: &[String],
+        domain: Option<&str>,
+    ) -> Vec<String> {
+        let eft = self
+            .model
+            .get_tokens("p", "p")
+            .and_then(|tokens| tokens.iter().position(|token| token == "p_eft"));
+        let is_deny = |rule: &Vec<String>| eft.and_then(|i| rule.get(i)).is_some_and(|eft| eft == "deny");
+
+        // a deny rule applies whatever the effect asked for in `field_values`
+        let len = eft.map_or(field_values.len(), |i| field_values.len().min(i.saturating_sub(1)));
+        let denied = self.get_implicit_subjects(
+            self.get_filtered_policy(1, &field_values[..len])
+                .into_iter()
+                .filter(is_deny),
+            domain,
+        );
+        let allowed = self.get_implicit_subjects(
+            self.get_filtered_policy(1, field_values)
+                .into_iter()
+                .filter(|rule| !is_deny(rule)),
+            domain,
+        );
+
+        allowed
+            .into_iter()
+            .filter(|user| !roles.contains(user) && !denied.contains(user))
+            .collect()
+    }
+
+    /// Gets the subjects of `rules` and the users inheriting them, in order and without duplicates.
+    fn get_implicit_subjects<I: Iterator<Item = Vec<String>>>(&self, rules: I, domain: Option<&str>) -> Vec<String> {
+        let mut subjects: Vec<String> = Vec::new();
+
+        for rule in rules.filter(|rule| !rule.is_empty()) {
+            let mut names = vec![rule[0].clone()];
+            names.extend(self.get_implicit_users_for_role(&rule[0], domain));
+
+            for name in names {
+                if !subjects.contains(&name) {
+                    subjects.push(name);
                 }
             }
         }
-        return res;
+
+        subjects
     }
 
-    pub fn get_implicit_permissions_for_user(&self, name: &str) -> Vec<Vec<String>>{
-        let mut roles = self.get_implicit_roles_for_user(name);
+    /// Gets implicit permissions for a user or role.
+    /// Compared to get_permissions_for_user(), this function retrieves permissions for inherited roles.
+    /// When `domain` is given, only the permissions in that domain are returned.
+    pub fn get_implicit_permissions_for_user(&self, name: &str, domain: Option<&str>) -> Vec<Vec<String>>{
+        let mut roles = self.get_implicit_roles_for_user(name, domain);
         roles.insert(0, name.to_string());
 
         let mut result: Vec<Vec<String>> = Vec::new();
 
         for role in roles{
-            let mut permission = self.get_permissions_for_user(&role);
+            let mut permission = match domain {
+                Some(domain) => self.get_permissions_for_user_in_domain(&role, domain),
+                None => self.get_permissions_for_user(&role),
+            };
             permission.retain(|v| !v.is_empty());
             result.extend(permission.clone());
         }
@@ -156,7 +364,6 @@ mod tests {
     use crate::util::{set_equals, array_2_d_equals};
     use crate::model::Model;
     use crate::persist::file_adapter::FileAdapter;
-    use crate::util::array_equals;
 
     #[test]
     fn test_role_api(){
@@ -173,32 +380,35 @@ mod tests {
         assert_eq!(enforcer.has_role_for_user("alice", "data1_admin", None), false);
         assert_eq!(enforcer.has_role_for_user("alice", "data2_admin", None), true);
 
-        enforcer.add_role_for_user("alice", "data1_admin");
+        enforcer.add_role_for_user("alice", "data1_admin").unwrap();
 
         assert_eq!(set_equals(enforcer.get_roles_for_user("alice", None), vec!["data2_admin".to_owned(), "data1_admin".to_owned()]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("bob", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("data2_admin", None), vec![]), true);
 
-        enforcer.delete_role_for_user("alice", "data1_admin");
+        enforcer.delete_role_for_user("alice", "data1_admin").unwrap();
 
         assert_eq!(set_equals(enforcer.get_roles_for_user("alice", None), vec!["data2_admin".to_owned()]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("bob", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("data2_admin", None), vec![]), true);
 
-        enforcer.delete_roles_for_user("alice");
+        enforcer.delete_roles_for_user("alice").unwrap();
 
         assert_eq!(set_equals(enforcer.get_roles_for_user("alice", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("bob", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("data2_admin", None), vec![]), true);
 
-        enforcer.add_role_for_user("alice", "data1_admin");
-        enforcer.delete_user("alice");
+        enforcer.add_role_for_user("alice", "data1_admin").unwrap();
+        // Deleting a user also removes its own permissions.
+        assert_eq!(enforcer.delete_user("alice").unwrap(), true);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read"]), false);
+        enforcer.add_permission_for_user("alice", &["data1", "read"]).unwrap();
 
         assert_eq!(set_equals(enforcer.get_roles_for_user("alice", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("bob", None), vec![]), true);
         assert_eq!(set_equals(enforcer.get_roles_for_user("data2_admin", None), vec![]), true);
 
-        enforcer.add_role_for_user("alice", "data2_admin");
+        enforcer.add_role_for_user("alice", "data2_admin").unwrap();
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -209,7 +419,8 @@ mod tests {
         assert_eq!(enforcer.enforce("bob", "data2", "read").unwrap(), false);
         assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), true);
 
-        enforcer.delete_role("data2_admin");
+        assert_eq!(enforcer.delete_role("data2_admin").unwrap(), true);
+        assert_eq!(enforcer.has_permission_for_user("data2_admin", &["data2", "read"]), false);
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -240,14 +451,14 @@ mod tests {
         assert_eq!(enforcer.has_permission_for_user("bob", &vec!["read"]), false);
         assert_eq!(enforcer.has_permission_for_user("bob", &vec!["write"]), true);
 
-        enforcer.delete_permission(vec!["read"]);
+        enforcer.delete_permission(vec!["read"]).unwrap();
 
         assert_eq!(enforcer.enforce_without_users("alice",  "read" ).unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("alice", "write").unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("bob", "read").unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("bob", "write").unwrap(), true);
 
-        enforcer.add_permission_for_user("bob", &vec!["read"]);
+        enforcer.add_permission_for_user("bob", &vec!["read"]).unwrap();
 
         assert_eq!(enforcer.enforce_without_users("alice",  "read" ).unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("alice", "write").unwrap(), false);
@@ -255,14 +466,14 @@ mod tests {
         assert_eq!(enforcer.enforce_without_users("bob", "write").unwrap(), true);
 
 
-        enforcer.delete_permission_for_user("bob", &vec!["read"]);
+        enforcer.delete_permission_for_user("bob", &vec!["read"]).unwrap();
 
         assert_eq!(enforcer.enforce_without_users("alice",  "read" ).unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("alice", "write").unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("bob", "read").unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("bob", "write").unwrap(), true);
 
-        enforcer.delete_permissions_for_user(&vec!["bob"] );
+        enforcer.delete_permissions_for_user(&vec!["bob"] ).unwrap();
 
         assert_eq!(enforcer.enforce_without_users("alice",  "read" ).unwrap(), false);
         assert_eq!(enforcer.enforce_without_users("alice", "write").unwrap(), false);
@@ -279,8 +490,8 @@ mod tests {
         assert_eq!(array_2_d_equals(&enforcer.get_permissions_for_user("alice"), &vec![vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()]]), true);
         assert_eq!(array_2_d_equals(&enforcer.get_permissions_for_user("bob"), &vec![vec!["bob".to_owned(), "data2".to_owned(), "write".to_owned()]]), true);
 
-        assert_eq!(array_equals(&enforcer.get_implicit_roles_for_user("alice"), &vec!["admin".to_string(), "data1_admin".to_string(), "data2_admin".to_string()]), true);
-        assert_eq!(array_equals(&enforcer.get_implicit_roles_for_user("bob"), &vec![]), true);
+        assert_eq!(enforcer.get_implicit_roles_for_user("alice", None), ["admin", "data1_admin", "data2_admin"]);
+        assert_eq!(enforcer.get_implicit_roles_for_user("bob", None), Vec::<String>::new());
     }
 
     #[test]
@@ -292,7 +503,7 @@ mod tests {
         assert_eq!(array_2_d_equals(&enforcer.get_permissions_for_user("alice"), &vec![vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()]]), true);
         assert_eq!(array_2_d_equals(&enforcer.get_permissions_for_user("bob"), &vec![vec!["bob".to_owned(), "data2".to_owned(), "write".to_owned()]]), true);
 
-        assert_eq!(array_2_d_equals(&enforcer.get_implicit_permissions_for_user("alice"),
+        assert_eq!(array_2_d_equals(&enforcer.get_implicit_permissions_for_user("alice", None),
                                     &vec![
                                         vec!["alice".to_string(), "data1".to_string(), "read".to_string()],
                                         vec!["data1_admin".to_string(), "data1".to_string(), "read".to_string()],
@@ -301,6 +512,137 @@ mod tests {
                                         vec!["data2_admin".to_string(), "data2".to_string(), "write".to_string()]
                                     ]), true);
 
-        assert_eq!(array_2_d_equals(&enforcer.get_implicit_permissions_for_user("bob"), &vec![vec!["bob".to_string(), "data2".to_string(), "write".to_string()]]), true);
+        assert_eq!(array_2_d_equals(&enforcer.get_implicit_permissions_for_user("bob", None), &vec![vec!["bob".to_string(), "data2".to_string(), "write".to_string()]]), true);
+    }
+
+    #[test]
+    fn test_domain_api(){
+        let model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_domains_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.get_roles_for_user_in_domain("alice", "domain1"), ["admin"]);
+        assert_eq!(enforcer.get_roles_for_user_in_domain("alice", "domain2"), Vec::<String>::new());
+        assert_eq!(enforcer.get_users_for_role_in_domain("admin", "domain2"), ["bob"]);
+        assert_eq!(enforcer.has_role_for_user("bob", "admin", Some("domain2")), true);
+
+        assert_eq!(enforcer.get_all_domains(), ["domain1", "domain2"]);
+        assert_eq!(enforcer.get_domains_for_user("alice"), ["domain1"]);
+
+        assert_eq!(enforcer.get_permissions_for_user_in_domain("admin", "domain1"),
+                   [["admin", "domain1", "data1", "read"], ["admin", "domain1", "data1", "write"]]);
+        assert_eq!(enforcer.get_implicit_roles_for_user("alice", Some("domain1")), ["admin"]);
+        assert_eq!(enforcer.get_implicit_permissions_for_user("alice", Some("domain1")),
+                   [["admin", "domain1", "data1", "read"], ["admin", "domain1", "data1", "write"]]);
+        assert_eq!(enforcer.get_implicit_permissions_for_user("alice", Some("domain2")), Vec::<Vec<String>>::new());
+
+        assert_eq!(enforcer.add_role_for_user_in_domain("alice", "admin", "domain2").unwrap(), true);
+        assert_eq!(enforcer.add_role_for_user_in_domain("alice", "admin", "domain2").unwrap(), false);
+        assert_eq!(enforcer.get_domains_for_user("alice"), ["domain1", "domain2"]);
+        assert_eq!(enforcer.enforce_with_domain("alice", "domain2", "data2", "read").unwrap(), true);
+
+        assert_eq!(enforcer.delete_roles_for_user_in_domain("alice", "domain1").unwrap(), true);
+        assert_eq!(enforcer.delete_roles_for_user_in_domain("alice", "domain1").unwrap(), false);
+        assert_eq!(enforcer.get_roles_for_user_in_domain("alice", "domain1"), Vec::<String>::new());
+        assert_eq!(enforcer.get_roles_for_user_in_domain("alice", "domain2"), ["admin"]);
+        assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), false);
+
+        assert_eq!(enforcer.delete_role_for_user_in_domain("alice", "admin", "domain2").unwrap(), true);
+        assert_eq!(enforcer.get_domains_for_user("alice"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_implicit_user_api(){
+        let model = Model::from_file("examples/rbac_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_hierarchy_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.get_implicit_users_for_role("data1_admin", None), ["admin", "alice"]);
+        assert_eq!(enforcer.get_implicit_users_for_role("alice", None), Vec::<String>::new());
+
+        assert_eq!(enforcer.get_implicit_users_for_permission(&["data1", "read"]), ["alice"]);
+        assert_eq!(enforcer.get_implicit_users_for_permission(&["data2", "write"]), ["bob", "alice"]);
+        assert_eq!(enforcer.get_implicit_users_for_permission(&["data3", "read"]), Vec::<String>::new());
+
+        enforcer.add_role_for_user("eve", "data1_admin").unwrap();
+        assert_eq!(enforcer.get_implicit_users_for_permission(&["data1", "write"]), ["eve", "alice"]);
+    }
+
+    #[test]
+    fn test_implicit_user_api_with_domain(){
+        let model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_domains_policy.csv", false);
+        let enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.get_implicit_users_for_role("admin", Some("domain1")), ["alice"]);
+        assert_eq!(enforcer.get_implicit_users_for_permission_in_domain(&["data1", "read"], "domain1"), ["alice"]);
+        assert_eq!(enforcer.get_implicit_users_for_permission_in_domain(&["data2", "read"], "domain2"), ["bob"]);
+        assert_eq!(enforcer.get_implicit_users_for_permission_in_domain(&["data2", "read"], "domain1"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_implicit_user_api_with_deny() {
+        let model = Model::from_file("examples/rbac_with_deny_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_deny_policy.csv", false);
+        let enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        // alice is granted data2 write through data2_admin but denied it directly
+        assert_eq!(enforcer.get_implicit_users_for_permission(&["data2", "write"]), ["bob"]);
+        assert_eq!(
+            enforcer.get_implicit_users_for_permission(&["data2", "write", "allow"]),
+            ["bob"]
+        );
+        assert_eq!(
+            enforcer.get_implicit_users_for_permission(&["data2", "read"]),
+            ["alice"]
+        );
+    }
+
+    #[test]
+    fn test_full_permission_api(){
+        let model = Model::from_file("examples/rbac_with_deny_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_deny_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read", "allow"]), true);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read"]), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["", "read", "allow"]), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read", "deny"]), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "write", "allow"]), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read", "allow", "extra"]), false);
+
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data2", "read", "allow"]), false);
+        assert_eq!(enforcer.has_implicit_permission_for_user("alice", &["data2", "read", "allow"]), true);
+        assert_eq!(enforcer.has_implicit_permission_for_user("bob", &["data2", "read", "allow"]), false);
+
+        assert_eq!(enforcer.delete_permission_for_user("alice", &["data1", "write", "allow"]).unwrap(), false);
+        assert_eq!(enforcer.delete_permission_for_user("alice", &["data1"]).unwrap(), false);
+        assert_eq!(enforcer.delete_permission_for_user("alice", &["", "read", "allow"]).unwrap(), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read", "allow"]), true);
+        assert_eq!(enforcer.delete_permission_for_user("alice", &["data2", "write", "deny"]).unwrap(), true);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data2", "write", "deny"]), false);
+        assert_eq!(enforcer.has_permission_for_user("alice", &["data1", "read", "allow"]), true);
+    }
+
+    #[test]
+    fn test_delete_role_and_user(){
+        let model = Model::from_file("examples/rbac_with_resource_roles_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_resource_roles_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.enforce("alice", "data2", "write").unwrap(), true);
+        assert_eq!(enforcer.delete_role("data_group").unwrap(), true);
+        assert_eq!(enforcer.get_named_grouping_policy("g2"), Vec::<Vec<String>>::new());
+        assert_eq!(enforcer.enforce("alice", "data2", "write").unwrap(), false);
+        assert_eq!(enforcer.delete_role("data_group").unwrap(), false);
+
+        assert_eq!(enforcer.delete_role("data_group_admin").unwrap(), true);
+        assert_eq!(enforcer.get_grouping_policy(), Vec::<Vec<String>>::new());
+        assert_eq!(enforcer.get_policy(), [["alice", "data1", "read"], ["bob", "data2", "write"]]);
+
+        assert_eq!(enforcer.delete_user("alice").unwrap(), true);
+        assert_eq!(enforcer.get_policy(), [["bob", "data2", "write"]]);
+        assert_eq!(enforcer.delete_user("alice").unwrap(), false);
+        assert_eq!(enforcer.delete_user("non_exist").unwrap(), false);
     }
 }
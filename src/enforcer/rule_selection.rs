@@ -0,0 +1,203 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::model::REGEX_STRING_LITERAL;
+
+lazy_static! {
+    static ref REGEX_ROLE_CALL: Regex = Regex::new(r"^(g[0-9]*)\s*\((.*)\)$").unwrap();
+}
+
+/// A condition of a matcher that a policy rule must meet for the request to match it.
+///
+/// The tokens are in the escaped notation of the model, e.g. `r_sub` and `p_sub`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Predicate {
+    /// `request == policy`: the policy field must have the value of the request field.
+    Equal { request: String, policy: String },
+    /// `ptype(request, policy, fields...)`: the policy field must be the request field or one of the
+    /// roles it inherits in the role definition `ptype`, the other `fields` are request fields.
+    Role {
+        ptype: String,
+        request: String,
+        policy: String,
+        fields: Vec<String>,
+    },
+}
+
+impl Predicate {
+    /// Get the policy token whose field the predicate constrains.
+    pub fn policy_token(&self) -> &str {
+        match *self {
+            Predicate::Equal { ref policy, .. } | Predicate::Role { ref policy, .. } => policy,
+        }
+    }
+}
+
+/// Find the predicates that every rule matched by `matcher` meets.
+///
+/// Only the operands of a top-level conjunction are analysed, a matcher with a top-level `||` has no
+/// predicate since a rule may match through any of its alternatives. String literals are blanked out
+/// first, their content is neither an operator nor a parenthesis.
+pub(crate) fn find_predicates(matcher: &str) -> Vec<Predicate> {
+    let matcher = REGEX_STRING_LITERAL.replace_all(matcher, "''");
+    let operands = match conjunction_operands(&matcher) {
+        Some(operands) => operands,
+        None => return Vec::new(),
+    };
+
+    operands
+        .into_iter()
+        .filter_map(|operand| parse_predicate(strip_parentheses(operand.trim())))
+        .collect()
+}
+
+/// Split `matcher` on its top-level `&&`, returns None if it has a top-level `||`.
+fn conjunction_operands(matcher: &str) -> Option<Vec<&str>> {
+    let mut depth = 0;
+    let mut operands = Vec::new();
+    let mut start = 0;
+    for (i, c) in matcher.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '|' if depth == 0 && matcher[i..].starts_with("||") => return None,
+            '&' if depth == 0 && matcher[i..].starts_with("&&") => {
+                operands.push(&matcher[start..i]);
+                start = i + 2;
+            }
+            _ => {}
+        }
+    }
+    operands.push(&matcher[start..]);
+    Some(operands)
+}
+
+/// Remove the parentheses around the whole `operand`, e.g. `(r_sub == p_sub)`.
+fn strip_parentheses(mut operand: &str) -> &str {
+    while operand.starts_with('(') && closing_parenthesis(operand) == Some(operand.len() - 1) {
+        operand = operand[1..operand.len() - 1].trim();
+    }
+    operand
+}
+
+/// Get the position of the parenthesis closing the one `s` starts with.
+fn closing_parenthesis(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_predicate(operand: &str) -> Option<Predicate> {
+    if let Some(caps) = REGEX_ROLE_CALL.captures(operand) {
+        let args: Vec<&str> = caps[2].split(',').map(|arg| arg.trim()).collect();
+        if args.len() < 2 || !is_token('r', args[0]) || !is_token('p', args[1]) {
+            return None;
+        }
+        if !args[2..].iter().all(|arg| is_token('r', arg)) {
+            return None;
+        }
+        return Some(Predicate::Role {
+            ptype: caps[1].to_string(),
+            request: args[0].to_string(),
+            policy: args[1].to_string(),
+            fields: args[2..].iter().map(|arg| arg.to_string()).collect(),
+        });
+    }
+
+    let mut sides = operand.splitn(2, "==").map(|side| side.trim());
+    let (left, right) = (sides.next()?, sides.next()?);
+    let (request, policy) = if is_token('r', left) && is_token('p', right) {
+        (left, right)
+    } else if is_token('p', left) && is_token('r', right) {
+        (right, left)
+    } else {
+        return None;
+    };
+    Some(Predicate::Equal {
+        request: request.to_string(),
+        policy: policy.to_string(),
+    })
+}
+
+/// Determine whether `s` is a token of the request `r` or the policy `p`, e.g. `r_sub`.
+fn is_token(sec: char, s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next() == Some(sec)
+        && chars.next() == Some('_')
+        && s.len() > 2
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::to_strings;
+
+    fn equal(request: &str, policy: &str) -> Predicate {
+        Predicate::Equal {
+            request: request.to_string(),
+            policy: policy.to_string(),
+        }
+    }
+
+    fn role(ptype: &str, request: &str, policy: &str, fields: &[&str]) -> Predicate {
+        Predicate::Role {
+            ptype: ptype.to_string(),
+            request: request.to_string(),
+            policy: policy.to_string(),
+            fields: to_strings(fields),
+        }
+    }
+
+    #[test]
+    fn test_find_predicates() {
+        assert_eq!(
+            find_predicates("r_sub == p_sub && r_obj == p_obj && r_act == p_act"),
+            vec![
+                equal("r_sub", "p_sub"),
+                equal("r_obj", "p_obj"),
+                equal("r_act", "p_act")
+            ]
+        );
+        assert_eq!(
+            find_predicates("(r_sub == p_sub) && keyMatch(r_obj, p_obj) && (p_act == r_act)"),
+            vec![equal("r_sub", "p_sub"), equal("r_act", "p_act")]
+        );
+        assert_eq!(
+            find_predicates("g(r_sub, p_sub, r_dom) && g2(r_obj, p_obj) && r_dom == p_dom"),
+            vec![
+                role("g", "r_sub", "p_sub", &["r_dom"]),
+                role("g2", "r_obj", "p_obj", &[]),
+                equal("r_dom", "p_dom")
+            ]
+        );
+
+        assert_eq!(
+            find_predicates("r_sub == p_sub && r_obj == p_obj || r_sub == \"root\""),
+            vec![]
+        );
+        assert_eq!(find_predicates("!(r_sub == p_sub && r_obj == p_obj)"), vec![]);
+        assert_eq!(find_predicates("(r_sub == p_sub) || (r_obj == p_obj)"), vec![]);
+        assert_eq!(find_predicates("g(p_sub, r_sub) && g(r_sub, p_sub, p_dom)"), vec![]);
+        assert_eq!(find_predicates("r_sub == r_obj.Owner && r_act == 'read'"), vec![]);
+        assert_eq!(
+            find_predicates(r#"r_sub == p_sub && r_obj == ")" || r_act == "(""#),
+            vec![]
+        );
+        assert_eq!(
+            find_predicates("r_sub == p_sub && r_obj == 'a && b' && g(r_sub, p_sub, ')')"),
+            vec![equal("r_sub", "p_sub")]
+        );
+    }
+}
@@ -1,17 +1,143 @@
+//! The errors returned by casbin.
+//!
+//! Every fallible function returns an `Error`, whose variants group the errors by their origin so
+//! that they can be matched on: the configuration text, the model, a policy rule, the adapter, the
+//! role manager or the evaluation of a matcher.
+
+use std::error::Error as StdError;
 use std::fmt;
 use std::io;
 
 #[derive(Debug)]
 pub enum Error {
+    /// Reading or writing a model or a configuration failed.
     Io(io::Error),
-    UnsupportedEffect,
-    // Temporary error type, we probably want more specific errors when we fail to parse.
-    ParsingFailure,
-    MissingKey,
-    InvalidValue,
-    InvalidSection(String),
+    Config(ConfigError),
+    Model(ModelError),
+    Policy(PolicyError),
+    Adapter(AdapterError),
+    RoleManager(RoleManagerError),
+    Matcher(MatcherError),
+    /// The operation is not supported, e.g. an adapter that does not save single policy rules.
+    NotImplemented,
+}
+
+/// An error in the text of a configuration or in one of its values.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The line `line` is not a section header, a `key = value` pair or a comment.
+    InvalidLine {
+        line: usize,
+        text: String,
+    },
+    MissingKey {
+        section: String,
+        key: String,
+    },
+    /// The value of `key` cannot be converted to the requested type.
+    InvalidValue {
+        section: String,
+        key: String,
+        value: String,
+    },
+}
+
+/// An error in the definition `key` of the model section `section`, e.g. `m` of `matchers`.
+#[derive(Debug)]
+pub struct ModelError {
+    pub section: String,
+    pub key: String,
+    pub kind: ModelErrorKind,
+}
+
+#[derive(Debug)]
+pub enum ModelErrorKind {
+    MissingDefinition,
+    InvalidDefinition(String),
+    /// A matcher uses a token that is not defined, e.g. `r.dom`.
+    UndefinedToken(String),
+    /// A matcher calls a role definition that is not defined, e.g. `g2`.
+    UndefinedFunction(String),
+    UnsupportedEffect(String),
+}
+
+/// A policy rule of type `ptype` that does not match its definition in the model.
+///
+/// `line` is the line of the rule in the text the policy was loaded from, if any.
+#[derive(Debug)]
+pub struct PolicyError {
+    pub ptype: String,
+    pub rule: Vec<String>,
+    pub line: Option<usize>,
+    pub reason: String,
+}
+
+/// An error of an adapter, with the error of its storage as source.
+#[derive(Debug)]
+pub struct AdapterError {
+    pub message: String,
+    source: Option<Box<dyn StdError + Send + Sync + 'static>>,
+}
+
+#[derive(Debug)]
+pub enum RoleManagerError {
     MissingRole(String),
-    Eval(eval::Error),
+    /// Linking the first name to the second one would make a role inherit from itself.
+    RoleCycle(String, String),
+}
+
+/// The matcher `matcher` cannot be evaluated.
+#[derive(Debug)]
+pub struct MatcherError {
+    pub matcher: String,
+    source: eval::Error,
+}
+
+impl ModelError {
+    pub fn new(section: &str, key: &str, kind: ModelErrorKind) -> Self {
+        ModelError {
+            section: section.to_string(),
+            key: key.to_string(),
+            kind,
+        }
+    }
+}
+
+impl PolicyError {
+    pub fn new<S: AsRef<str>>(ptype: &str, rule: &[S], line: Option<usize>, reason: &str) -> Self {
+        PolicyError {
+            ptype: ptype.to_string(),
+            rule: rule.iter().map(|s| s.as_ref().to_string()).collect(),
+            line,
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl AdapterError {
+    pub fn new(message: &str) -> Self {
+        AdapterError {
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    /// Create an AdapterError caused by the error `source` of the storage.
+    pub fn with_source<E: Into<Box<dyn StdError + Send + Sync + 'static>>>(message: &str, source: E) -> Self {
+        AdapterError {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+}
+
+impl MatcherError {
+    pub fn new(matcher: &str, source: eval::Error) -> Self {
+        MatcherError {
+            matcher: matcher.to_string(),
+            source,
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -20,9 +146,39 @@ impl From<io::Error> for Error {
     }
 }
 
-impl From<eval::Error> for Error {
-    fn from(error: eval::Error) -> Self {
-        Error::Eval(error)
+impl From<ConfigError> for Error {
+    fn from(error: ConfigError) -> Self {
+        Error::Config(error)
+    }
+}
+
+impl From<ModelError> for Error {
+    fn from(error: ModelError) -> Self {
+        Error::Model(error)
+    }
+}
+
+impl From<PolicyError> for Error {
+    fn from(error: PolicyError) -> Self {
+        Error::Policy(error)
+    }
+}
+
+impl From<AdapterError> for Error {
+    fn from(error: AdapterError) -> Self {
+        Error::Adapter(error)
+    }
+}
+
+impl From<RoleManagerError> for Error {
+    fn from(error: RoleManagerError) -> Self {
+        Error::RoleManager(error)
+    }
+}
+
+impl From<MatcherError> for Error {
+    fn from(error: MatcherError) -> Self {
+        Error::Matcher(error)
     }
 }
 
@@ -30,13 +186,132 @@ impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Io(ref err) => write!(f, "IO error: {}", err),
-            Error::UnsupportedEffect => write!(f, "Unsupported effect"),
-            Error::ParsingFailure => write!(f, "Parsing failure"),
-            Error::MissingKey => write!(f, "Missing key in configuration"),
-            Error::InvalidValue => write!(f, "Invalid value in configuration"),
-            Error::InvalidSection(ref name) => write!(f, "Invalid section: {}", name),
-            Error::MissingRole(ref name) => write!(f, "Missing role: {}", name),
-            Error::Eval(ref err) => write!(f, "Evaluation error: {}", err),
+            Error::Config(ref err) => write!(f, "Invalid configuration: {}", err),
+            Error::Model(ref err) => write!(f, "Invalid model: {}", err),
+            Error::Policy(ref err) => write!(f, "Invalid policy rule: {}", err),
+            Error::Adapter(ref err) => write!(f, "Adapter error: {}", err),
+            Error::RoleManager(ref err) => write!(f, "Role manager error: {}", err),
+            Error::Matcher(ref err) => write!(f, "Matcher error: {}", err),
+            Error::NotImplemented => write!(f, "Not implemented"),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::InvalidLine { line, ref text } => write!(f, "invalid line {}: {}", line, text),
+            ConfigError::MissingKey { ref section, ref key } => write!(f, "missing key {} in [{}]", key, section),
+            ConfigError::InvalidValue {
+                ref section,
+                ref key,
+                ref value,
+            } => write!(f, "invalid value of {} in [{}]: {}", key, section, value),
+        }
+    }
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] {}: ", self.section, self.key)?;
+        match self.kind {
+            ModelErrorKind::MissingDefinition => write!(f, "missing definition"),
+            ModelErrorKind::InvalidDefinition(ref reason) => write!(f, "{}", reason),
+            ModelErrorKind::UndefinedToken(ref token) => write!(f, "undefined token {}", token),
+            ModelErrorKind::UndefinedFunction(ref function) => write!(f, "undefined function {}", function),
+            ModelErrorKind::UnsupportedEffect(ref effect) => write!(f, "unsupported effect {}", effect),
+        }
+    }
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}, {}", self.ptype, self.rule.join(", "))?;
+        if let Some(line) = self.line {
+            write!(f, " (line {})", line)?;
+        }
+        write!(f, ": {}", self.reason)
+    }
+}
+
+// The errors with a source only describe their own context, the source is part of the error chain.
+impl fmt::Display for AdapterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl fmt::Display for RoleManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RoleManagerError::MissingRole(ref name) => write!(f, "missing role {}", name),
+            RoleManagerError::RoleCycle(ref name1, ref name2) => {
+                write!(f, "linking {} to {} makes a role inheritance cycle", name1, name2)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MatcherError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "cannot evaluate {}", self.matcher)
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match *self {
+            Error::Io(ref err) => Some(err),
+            Error::Config(ref err) => Some(err),
+            Error::Model(ref err) => Some(err),
+            Error::Policy(ref err) => Some(err),
+            Error::Adapter(ref err) => Some(err),
+            Error::RoleManager(ref err) => Some(err),
+            Error::Matcher(ref err) => Some(err),
+            Error::NotImplemented => None,
         }
     }
 }
+
+impl StdError for ConfigError {}
+
+impl StdError for ModelError {}
+
+impl StdError for PolicyError {}
+
+impl StdError for AdapterError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.source {
+            Some(ref source) => Some(source.as_ref()),
+            None => None,
+        }
+    }
+}
+
+impl StdError for RoleManagerError {}
+
+impl StdError for MatcherError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        Some(&self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_source() {
+        let io_error = io::Error::new(io::ErrorKind::NotFound, "policy.csv");
+        let err = Error::from(AdapterError::with_source("cannot read the policy", io_error));
+
+        assert_eq!(err.to_string(), "Adapter error: cannot read the policy");
+        let adapter_error = err.source().unwrap();
+        assert_eq!(adapter_error.to_string(), "cannot read the policy");
+        assert_eq!(adapter_error.source().unwrap().to_string(), "policy.csv");
+
+        let boxed: Box<dyn StdError + Send + Sync> =
+            Box::new(Error::from(RoleManagerError::MissingRole("admin".to_string())));
+        assert_eq!(boxed.to_string(), "Role manager error: missing role admin");
+    }
+}
@@ -2,7 +2,7 @@ pub mod assertion;
 pub mod config;
 pub mod effect;
 pub mod enforcer;
-mod error;
+pub mod error;
 pub mod model;
 pub mod persist;
 pub mod rbac;
@@ -3,38 +3,52 @@ use std::fs;
 use std::path::Path;
 
 use lazy_static::lazy_static;
+use regex::Regex;
 
 use crate::assertion::Assertion;
 use crate::config::Config;
-use crate::error::Error;
-use crate::util::{escape_assertion, remove_comments};
+use crate::effect::SUPPORTED_EFFECTS;
+use crate::error::{Error, ModelError, ModelErrorKind, PolicyError};
+use crate::util::{escape_assertion, parse_csv_line, remove_comments, unescape_assertion};
 
+mod builder;
 mod function;
 pub mod policy;
+mod rule_set;
 
+pub use crate::model::builder::ModelBuilder;
 pub use crate::model::function::{get_function_map, FunctionMap};
+pub(crate) use crate::model::rule_set::RuleSet;
 
 type AssertionMap = HashMap<String, Assertion>;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Model {
-    pub data: HashMap<String, AssertionMap>,
+    data: HashMap<String, AssertionMap>,
 }
 
 lazy_static! {
-    static ref SECTION_NAME_MAP: HashMap<&'static str, &'static str> = {
-        let mut map = HashMap::new();
-        map.insert("r", "request_definition");
-        map.insert("p", "policy_definition");
-        map.insert("g", "role_definition");
-        map.insert("e", "policy_effect");
-        map.insert("m", "matchers");
-        map
-    };
+    // a token of a request or a policy in a matcher, e.g. `r.sub`, `r_sub` or `p2.obj`
+    static ref REGEX_TOKEN: Regex = Regex::new(r"(^|[^\w.])([rp][0-9]*)[._](\w+)").unwrap();
+    // a call to a role definition in a matcher, e.g. `g(` or `g2 (`
+    static ref REGEX_ROLE_CALL: Regex = Regex::new(r"(^|[^\w.])(g[0-9]*)\s*\(").unwrap();
+    pub(crate) static ref REGEX_STRING_LITERAL: Regex = Regex::new(r#"'[^']*'|"[^"]*""#).unwrap();
 }
 
-fn get_section_name(name: &str) -> &str {
-    SECTION_NAME_MAP.get(name).unwrap()
+fn get_section_name(sec: &str) -> Option<&'static str> {
+    match sec {
+        "r" => Some("request_definition"),
+        "p" => Some("policy_definition"),
+        "g" => Some("role_definition"),
+        "e" => Some("policy_effect"),
+        "m" => Some("matchers"),
+        _ => None,
+    }
+}
+
+/// Create the error of the definition `key` of the section `sec`, named as in a `.conf` file.
+pub(crate) fn model_error(sec: &str, key: &str, kind: ModelErrorKind) -> Error {
+    Error::Model(ModelError::new(get_section_name(sec).unwrap_or(sec), key, kind))
 }
 
 fn get_section_value(sec: &str, i: i32) -> String {
@@ -62,6 +76,11 @@ impl Model {
         Model { data: HashMap::new() }
     }
 
+    /// Start building a Model from its definitions, see `ModelBuilder`.
+    pub fn builder() -> ModelBuilder {
+        ModelBuilder::new()
+    }
+
     /// Create a Model instance from a file.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
         let text = fs::read_to_string(path)?;
@@ -79,9 +98,83 @@ impl Model {
         model.load_section(&cfg, "matchers")?;
         model.load_section(&cfg, "role_definition")?;
 
+        model.validate()?;
         Ok(model)
     }
 
+    /// Check that the model can be enforced.
+    ///
+    /// The request, policy, effect and matcher definitions are required, matchers may only use the
+    /// tokens of the request and policy definitions and call the defined role definitions, and the
+    /// policy effect must be one of `SUPPORTED_EFFECTS`.
+    pub fn validate(&self) -> Result<(), Error> {
+        for sec in &["r", "p", "e", "m"] {
+            if self.get_assertion(sec, sec).is_none() {
+                return Err(model_error(sec, sec, ModelErrorKind::MissingDefinition));
+            }
+        }
+
+        for (key, assertion) in &self.data["e"] {
+            if !SUPPORTED_EFFECTS.contains(&assertion.value.as_str()) {
+                let effect = unescape_assertion(&assertion.value);
+                return Err(model_error("e", key, ModelErrorKind::UnsupportedEffect(effect)));
+            }
+        }
+
+        for (key, assertion) in &self.data["m"] {
+            let matcher = REGEX_STRING_LITERAL.replace_all(&assertion.value, "''");
+
+            for caps in REGEX_TOKEN.captures_iter(&matcher) {
+                let token = format!("{}_{}", &caps[2], &caps[3]);
+                let sec = &caps[2][..1];
+                let defined = self.get_assertion(sec, &caps[2]).is_some_and(|ast| ast.tokens.contains(&token));
+                if !defined {
+                    let token = format!("{}.{}", &caps[2], &caps[3]);
+                    return Err(model_error("m", key, ModelErrorKind::UndefinedToken(token)));
+                }
+            }
+
+            for caps in REGEX_ROLE_CALL.captures_iter(&matcher) {
+                if self.get_assertion("g", &caps[2]).is_none() {
+                    let function = caps[2].to_string();
+                    return Err(model_error("m", key, ModelErrorKind::UndefinedFunction(function)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Determine whether the section `sec` (`r`, `p`, `g`, `e` or `m`) has a definition.
+    pub fn has_section(&self, sec: &str) -> bool {
+        self.data.get(sec).is_some_and(|assertions| !assertions.is_empty())
+    }
+
+    /// Get the definition `key` of the section `sec`, e.g. `p2` of `p`.
+    pub fn get_assertion(&self, sec: &str, key: &str) -> Option<&Assertion> {
+        self.data.get(sec).and_then(|assertions| assertions.get(key))
+    }
+
+    /// Get the definitions of the section `sec`, sorted by key.
+    pub fn get_assertions(&self, sec: &str) -> Vec<&Assertion> {
+        let mut assertions: Vec<&Assertion> = match self.data.get(sec) {
+            Some(assertions) => assertions.values().collect(),
+            None => Vec::new(),
+        };
+        assertions.sort_by(|a, b| a.key.cmp(&b.key));
+        assertions
+    }
+
+    /// Get the tokens of the request or policy definition `key`, e.g. `p_sub`, `p_obj`, `p_act` for `p`.
+    pub fn get_tokens(&self, sec: &str, key: &str) -> Option<&[String]> {
+        self.get_assertion(sec, key).map(|assertion| assertion.tokens())
+    }
+
+    /// Get the rules of the policy type `ptype` without copying them.
+    pub fn get_rules(&self, sec: &str, ptype: &str) -> Option<&[Vec<String>]> {
+        self.get_assertion(sec, ptype).map(|assertion| assertion.policy())
+    }
+
     fn load_assertion(&mut self, cfg: &Config, sec: &str, key: &str) -> Result<bool, Error> {
         if let Some(value) = cfg.string(key, Some(sec)) {
             self.add_def(shorthand_section_name(sec).unwrap(), key, value.as_str())
@@ -93,7 +186,7 @@ impl Model {
     pub(crate) fn add_def(&mut self, sec: &str, key: &str, value: &str) -> Result<bool, Error> {
         let mut assertion = Assertion::new();
 
-        if value == "" {
+        if value.is_empty() {
             return Ok(false);
         }
 
@@ -119,56 +212,89 @@ impl Model {
 
     fn load_section(&mut self, cfg: &Config, section: &str) -> Result<(), Error> {
         let mut i = 1;
-        while self
-            .load_assertion(
-                cfg,
-                section,
-                get_section_value(shorthand_section_name(section).unwrap(), i).as_str(),
-            )
-            .unwrap()
-        {
+        while self.load_assertion(
+            cfg,
+            section,
+            get_section_value(shorthand_section_name(section).unwrap(), i).as_str(),
+        )? {
             i += 1;
         }
         Ok(())
     }
 
+    /// Print every assertion of the model as `section.key: value`.
     pub fn print_model(&self) -> Result<(), Error> {
-        unimplemented!()
+        for sec in &["r", "p", "g", "e", "m"] {
+            if let Some(assertions) = self.data.get(*sec) {
+                let mut keys: Vec<&String> = assertions.keys().collect();
+                keys.sort();
+                for key in keys {
+                    println!("{}.{}: {}", sec, key, assertions[key].value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the model as the text of a `.conf` file.
+    ///
+    /// The sections are written in the standard order with the `r.`/`p.` notation of the original text,
+    /// comments are not kept. The text is parsed back by `from_string` into an equal model.
+    pub fn to_text(&self) -> String {
+        let mut cfg = Config::empty();
+        for sec in &["r", "p", "g", "e", "m"] {
+            if let (Some(assertions), Some(name)) = (self.data.get(*sec), get_section_name(sec)) {
+                for (key, assertion) in assertions {
+                    let value = match *sec {
+                        "r" | "p" => assertion.value.clone(),
+                        _ => unescape_assertion(&assertion.value),
+                    };
+                    cfg.set(key, &value, Some(name));
+                }
+            }
+        }
+        cfg.to_string()
     }
 
     /// Load a policy rule from a line of text.
     pub fn load_policy_line(&mut self, line: &str) -> Result<(), Error> {
+        self.load_rule(line, None)
+    }
+
+    /// Load a policy rule from the line `number` of a text, the number is reported in errors.
+    pub fn load_numbered_policy_line(&mut self, line: &str, number: usize) -> Result<(), Error> {
+        self.load_rule(line, Some(number))
+    }
+
+    fn load_rule(&mut self, line: &str, number: Option<usize>) -> Result<(), Error> {
         if line.is_empty() || line.starts_with('#') {
             return Ok(());
         }
 
-        let tokens: Vec<&str> = line.split(',').map(|t| t.trim()).collect();
+        let mut tokens = match parse_csv_line(line) {
+            Some(tokens) => tokens,
+            None => {
+                let mut fields = line.splitn(2, ',').map(|s| s.trim());
+                let ptype = fields.next().unwrap_or_default();
+                let reason = "unterminated or misplaced quote";
+                return Err(PolicyError::new(ptype, &fields.collect::<Vec<_>>(), number, reason).into());
+            }
+        };
 
         if tokens.len() < 2 {
-            return Err(Error::InvalidValue);
-        }
-
-        let key = tokens[0];
-        let section = &key[0..1];
-
-        if !self.data.contains_key(section) {
-            self.data.insert(String::from(section), AssertionMap::new());
-        }
-
-        let assertion_map = self.data.get_mut(section).unwrap();
-
-        if !assertion_map.contains_key(key) {
-            assertion_map.insert(key.to_string(), Assertion::new());
+            let ptype = tokens.first().map_or("", |t| t.as_str());
+            return Err(PolicyError::new::<&str>(ptype, &[], number, "the rule has no value").into());
         }
 
-        let assertion = assertion_map.get_mut(key).unwrap();
-        let mut value = vec![];
+        let value = tokens.split_off(1);
+        let key = tokens[0].as_str();
+        let section = key.get(..1).unwrap_or_default();
 
-        for s in &tokens[1..] {
-            value.push(String::from(*s));
-        }
+        self.check_rule(section, key, &value, number)?;
 
-        assertion.policy.push(value);
+        // a repeated rule is only loaded once
+        let assertion = self.data.get_mut(section).unwrap().get_mut(key).unwrap();
+        assertion.policy.insert(value);
 
         Ok(())
     }
@@ -181,7 +307,8 @@ mod tests {
     use crate::enforcer::DefaultEnforcer;
     use crate::util::builtin_operators;
     use crate::rbac::MatchingFunction;
-    use crate::model::AssertionMap;
+    use crate::error::{Error, ModelError, ModelErrorKind};
+    use crate::persist::Adapter;
 
     #[test]
     fn test_basic_model(){
@@ -202,8 +329,7 @@ mod tests {
     #[test]
     fn test_basic_model_no_policy(){
         let mut model = Model::from_file("examples/basic_model.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+        let mut enforcer = DefaultEnforcer::from_model(model).unwrap();
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), false);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -240,8 +366,7 @@ mod tests {
     /// TODO: Need to modify enforce function to return true if r_sub == root
     fn test_basic_model_with_root_no_policy(){
         let mut model = Model::from_file("examples/basic_with_root_model.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+        let mut enforcer = DefaultEnforcer::from_model(model).unwrap();
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), false);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -297,6 +422,18 @@ mod tests {
         assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), true);
     }
 
+    #[test]
+    fn test_rbac_model_in_multi_line() {
+        let model = Model::from_file("examples/rbac_model_in_multi_line.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
+        let enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
+        assert_eq!(enforcer.enforce("alice", "data2", "write").unwrap(), true);
+        assert_eq!(enforcer.enforce("bob", "data1", "read").unwrap(), false);
+        assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), true);
+    }
+
     #[test]
     fn test_rbac_model_with_resources_roles(){
         let mut model = Model::from_file("examples/rbac_with_resource_roles_model.conf").unwrap();
@@ -313,6 +450,22 @@ mod tests {
         assert_eq!(enforcer.enforce("bob", "data2", "write").unwrap(), true);
     }
 
+    #[test]
+    fn test_rbac_model_with_separate_role_managers(){
+        let mut model = Model::from_file("examples/rbac_with_resource_roles_model.conf").unwrap();
+        let adapter = FileAdapter::new("examples/rbac_with_resource_roles_policy.csv", false);
+        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+
+        assert_eq!(enforcer.has_named_role_for_user("g2", "data1", "data_group", None), true);
+        assert_eq!(enforcer.has_role_for_user("data1", "data_group", None), false);
+        assert_eq!(enforcer.get_named_roles_for_user("g", "alice", None), ["data_group_admin"]);
+        assert_eq!(enforcer.get_named_users_for_role("g2", "data_group", None).len(), 2);
+
+        // `data1` is only grouped with `data_group` as a resource, it must not act as a subject.
+        enforcer.add_policy(&["data_group", "data1", "read"]).unwrap();
+        assert_eq!(enforcer.enforce("data1", "data1", "read").unwrap(), false);
+    }
+
     #[test]
     fn test_rbac_model_with_domains(){
         let mut model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
@@ -332,16 +485,15 @@ mod tests {
     #[test]
     fn test_rbac_model_with_domains_at_runtime(){
         let mut model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+        let mut enforcer = DefaultEnforcer::from_model(model).unwrap();
 
-        enforcer.add_policy(&["admin", "domain1", "data1", "read"]);
-        enforcer.add_policy(&["admin", "domain1", "data1", "write"]);
-        enforcer.add_policy(&["admin", "domain2", "data2", "read"]);
-        enforcer.add_policy(&["admin", "domain2", "data2", "write"]);
+        enforcer.add_policy(&["admin", "domain1", "data1", "read"]).unwrap();
+        enforcer.add_policy(&["admin", "domain1", "data1", "write"]).unwrap();
+        enforcer.add_policy(&["admin", "domain2", "data2", "read"]).unwrap();
+        enforcer.add_policy(&["admin", "domain2", "data2", "write"]).unwrap();
 
-        enforcer.add_grouping_policy(&["alice", "admin", "domain1"]);
-        enforcer.add_grouping_policy(&["bob", "admin", "domain2"]);
+        enforcer.add_grouping_policy(&["alice", "admin", "domain1"]).unwrap();
+        enforcer.add_grouping_policy(&["bob", "admin", "domain2"]).unwrap();
 
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "write").unwrap(), true);
@@ -352,7 +504,7 @@ mod tests {
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "read").unwrap(), true);
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "write").unwrap(), true);
 
-        enforcer.remove_filtered_policy(1, &["domain1", "data1"]);
+        enforcer.remove_filtered_policy(1, &["domain1", "data1"]).unwrap();
 
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), false);
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "write").unwrap(), false);
@@ -363,7 +515,7 @@ mod tests {
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "read").unwrap(), true);
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "write").unwrap(), true);
 
-        enforcer.remove_policy(&["admin", "domain2", "data2", "read"]);
+        enforcer.remove_policy(&["admin", "domain2", "data2", "read"]).unwrap();
 
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), false);
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "write").unwrap(), false);
@@ -381,17 +533,17 @@ mod tests {
         let adapter = FileAdapter::new("examples/rbac_with_domains_policy.csv", false);
         let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
 
-        enforcer.add_policy(&["admin", "domain3", "data1", "read"]);
-        enforcer.add_grouping_policy(&["alice", "admin", "domain3"]);
+        enforcer.add_policy(&["admin", "domain3", "data1", "read"]).unwrap();
+        enforcer.add_grouping_policy(&["alice", "admin", "domain3"]).unwrap();
 
         assert_eq!(enforcer.enforce_with_domain("alice", "domain3", "data1", "read").unwrap(), true);
 
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), true);
-        enforcer.remove_filtered_policy(1, &["domain1", "data1"]);
+        enforcer.remove_filtered_policy(1, &["domain1", "data1"]).unwrap();
         assert_eq!(enforcer.enforce_with_domain("alice", "domain1", "data1", "read").unwrap(), false);
 
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "read").unwrap(), true);
-        enforcer.remove_policy(&["admin", "domain2", "data2", "read"]);
+        enforcer.remove_policy(&["admin", "domain2", "data2", "read"]).unwrap();
         assert_eq!(enforcer.enforce_with_domain("bob", "domain2", "data2", "read").unwrap(), false);
     }
 
@@ -431,7 +583,7 @@ mod tests {
         /// You can add custom data to a grouping policy. Casbin will ignore it. It is only meaningful to the caller.
         /// This feature can be used to store information like wether "bob" is an end user (so no subject will inherit "bob")
         /// For Casbin, it is equivalent to: enforcer.add_grouping_policy("bob", "data2_admin")
-        enforcer.add_grouping_policy(&["bob", "data2_admin", "custom_data"]);
+        enforcer.add_grouping_policy(&["bob", "data2_admin", "custom_data"]).unwrap();
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -445,7 +597,7 @@ mod tests {
         /// You should also take the custom data as a parameter when deleting a grouping policy.
         /// enforcer.remove_grouping_policy("bob", "data2_admin") won't work.
         /// Or you can remove it by using remove_filtered_grouping_policy().
-        enforcer.remove_grouping_policy(&["bob", "data2_admin", "custom_data"]);
+        enforcer.remove_grouping_policy(&["bob", "data2_admin", "custom_data"]).unwrap();
 
         assert_eq!(enforcer.enforce("alice", "data1", "read").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "data1", "write").unwrap(), false);
@@ -463,7 +615,7 @@ mod tests {
         let adapter = FileAdapter::new("examples/rbac_with_pattern_policy.csv", false);
         let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
 
-        enforcer.add_matching_function("keyMatch2", MatchingFunction(Box::new(builtin_operators::key_match2)));
+        enforcer.add_named_matching_function("g2", "keyMatch2", MatchingFunction(Box::new(builtin_operators::key_match2)));
 
         assert_eq!(enforcer.enforce("alice", "/book/1", "GET").unwrap(), true);
         assert_eq!(enforcer.enforce("alice", "/book/2", "GET").unwrap(), true);
@@ -499,8 +651,7 @@ mod tests {
     #[ignore]
     fn test_abac_model(){
         let mut model = Model::from_file("examples/abac_model.conf").unwrap();
-        let adapter = FileAdapter::new("examples/empty.csv", false);
-        let mut enforcer = DefaultEnforcer::new(model, adapter).unwrap();
+        let mut enforcer = DefaultEnforcer::from_model(model).unwrap();
     }
 
     #[test]
@@ -550,4 +701,134 @@ mod tests {
     fn test_key_match_custom_model(){
 
     }
+
+    #[test]
+    fn test_model_to_text() {
+        let model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
+        assert_eq!(
+            model.to_text(),
+            "[request_definition]
+r = sub, dom, obj, act
+
+[policy_definition]
+p = sub, dom, obj, act
+
+[role_definition]
+g = _, _, _
+
+[policy_effect]
+e = some(where (p.eft == allow))
+
+[matchers]
+m = g(r.sub, p.sub, r.dom) && r.dom == p.dom && r.obj == p.obj && r.act == p.act
+"
+        );
+
+        for path in &[
+            "examples/basic_model.conf",
+            "examples/rbac_model_in_multi_line.conf",
+            "examples/rbac_with_deny_model.conf",
+            "examples/rbac_with_resource_roles_model.conf",
+            "examples/abac_model.conf",
+            "examples/keymatch_model.conf",
+            "examples/priority_model.conf",
+        ] {
+            let model = Model::from_file(path).unwrap();
+            assert_eq!(Model::from_string(&model.to_text()).unwrap(), model, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_model_accessors() {
+        let mut model = Model::from_file("examples/rbac_with_resource_roles_model.conf").unwrap();
+        model.load_policy_line("p, alice, data1, read").unwrap();
+        model.load_policy_line("g2, data1, data_group").unwrap();
+
+        assert_eq!(model.has_section("g"), true);
+        assert_eq!(model.has_section("x"), false);
+        assert_eq!(model.get_tokens("r", "r").unwrap(), ["r_sub", "r_obj", "r_act"]);
+        assert_eq!(model.get_tokens("p", "p2"), None);
+        assert_eq!(model.get_rules("p", "p").unwrap(), [vec!["alice", "data1", "read"]]);
+        assert_eq!(model.get_rules("g", "g").unwrap().is_empty(), true);
+
+        let keys: Vec<&str> = model.get_assertions("g").iter().map(|a| a.key()).collect();
+        assert_eq!(keys, ["g", "g2"]);
+        let matcher = model.get_assertion("m", "m").unwrap();
+        assert_eq!(matcher.value(), "g(r_sub, p_sub) && g2(r_obj, p_obj) && r_act == p_act");
+        assert_eq!(matcher.tokens().is_empty(), true);
+    }
+
+    #[test]
+    fn test_filtered_policy_with_field_index() {
+        let mut model = Model::from_file("examples/rbac_with_domains_model.conf").unwrap();
+        model.add_field_index("p", "p", 1);
+        let adapter = FileAdapter::new("examples/rbac_with_domains_policy.csv", false);
+        adapter.load_policy(&mut model).unwrap();
+        model.add_policy("p", "p", &["admin", "domain1", "data3", "read"]).unwrap();
+
+        assert_eq!(
+            model.get_filtered_policy("p", "p", 1, &["domain1"]).unwrap(),
+            vec![
+                vec!["admin", "domain1", "data1", "read"],
+                vec!["admin", "domain1", "data1", "write"],
+                vec!["admin", "domain1", "data3", "read"],
+            ]
+        );
+        assert_eq!(
+            model.get_filtered_policy("p", "p", 0, &["admin", "domain2", "", "read"]).unwrap(),
+            vec![vec!["admin", "domain2", "data2", "read"]]
+        );
+
+        assert_eq!(model.remove_filtered_policy("p", "p", 1, &["domain1", "data1"]), true);
+        assert_eq!(
+            model.get_filtered_policy("p", "p", 1, &["domain1"]).unwrap(),
+            vec![vec!["admin", "domain1", "data3", "read"]]
+        );
+        assert_eq!(model.get_filtered_policy("p", "p", 1, &["domain3"]), None);
+    }
+
+    #[test]
+    fn test_validate_model() {
+        for entry in std::fs::read_dir("examples").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "conf") {
+                assert_eq!(Model::from_file(&path).is_ok(), true, "{:?}", path);
+            }
+        }
+
+        let model = |effect: &str, matcher: &str| {
+            Model::from_string(&format!(
+                "[request_definition]\nr = sub, obj, act\n\n[policy_definition]\np = sub, obj, act\n\n\
+                 [role_definition]\ng = _, _\n\n[policy_effect]\ne = {}\n\n[matchers]\nm = {}\n",
+                effect, matcher
+            ))
+        };
+        let allow = "some(where (p.eft == allow))";
+
+        assert_eq!(model(allow, "g(r.sub, p.sub) && r.obj in ('r.x', \"p.y\")").is_ok(), true);
+
+        let err = model(allow, "r.sub == p.sub && r.dom == p.obj").unwrap_err();
+        assert!(matches!(err, Error::Model(ModelError { kind: ModelErrorKind::UndefinedToken(_), .. })));
+        assert_eq!(err.to_string(), "Invalid model: [matchers] m: undefined token r.dom");
+        assert!(matches!(
+            model(allow, "r.sub == p2.sub"),
+            Err(Error::Model(ModelError { kind: ModelErrorKind::UndefinedToken(token), .. })) if token == "p2.sub"
+        ));
+        assert!(matches!(
+            model(allow, "g2(r.sub, p.sub) && r.obj == p.obj"),
+            Err(Error::Model(ModelError { kind: ModelErrorKind::UndefinedFunction(function), .. })) if function == "g2"
+        ));
+        let err = model("some(where (p.eft == maybe))", "r.sub == p.sub").unwrap_err();
+        assert!(matches!(err, Error::Model(ModelError { kind: ModelErrorKind::UnsupportedEffect(_), .. })));
+        assert_eq!(
+            err.to_string(),
+            "Invalid model: [policy_effect] e: unsupported effect some(where (p.eft == maybe))"
+        );
+
+        let without_matchers = "[request_definition]\nr = sub, obj, act\n\n[policy_definition]\n\
+                                p = sub, obj, act\n\n[policy_effect]\ne = some(where (p.eft == allow))\n";
+        let err = Model::from_string(without_matchers).unwrap_err();
+        assert!(matches!(err, Error::Model(ModelError { kind: ModelErrorKind::MissingDefinition, .. })));
+        assert_eq!(err.to_string(), "Invalid model: [matchers] m: missing definition");
+    }
 }
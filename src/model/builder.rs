@@ -0,0 +1,207 @@
+use crate::error::{Error, ModelErrorKind};
+use crate::model::{model_error, Model};
+
+/// Build a `Model` without going through the text of a `.conf` file.
+///
+/// ```
+/// use casbin::model::Model;
+///
+/// let model = Model::builder()
+///     .request(&["sub", "obj", "act"])
+///     .policy(&["sub", "obj", "act"])
+///     .role("g", 2)
+///     .effect("some(where (p.eft == allow))")
+///     .matcher("g(r.sub, p.sub) && r.obj == p.obj && r.act == p.act")
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// Each definition is validated as it is added, the first error is returned by `build`.
+#[derive(Debug)]
+pub struct ModelBuilder {
+    // the definitions as (section, key, value) in the notation of a `.conf` file
+    defs: Vec<(&'static str, String, String)>,
+    error: Option<Error>,
+}
+
+impl ModelBuilder {
+    pub(crate) fn new() -> Self {
+        ModelBuilder {
+            defs: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Define the fields of the request `r`.
+    pub fn request(self, tokens: &[&str]) -> Self {
+        self.named_request("r", tokens)
+    }
+
+    /// Define the fields of the request `key`, e.g. `r2`.
+    pub fn named_request(self, key: &str, tokens: &[&str]) -> Self {
+        self.add_tokens("r", key, tokens)
+    }
+
+    /// Define the fields of the policy `p`.
+    pub fn policy(self, tokens: &[&str]) -> Self {
+        self.named_policy("p", tokens)
+    }
+
+    /// Define the fields of the policy `key`, e.g. `p2`.
+    pub fn named_policy(self, key: &str, tokens: &[&str]) -> Self {
+        self.add_tokens("p", key, tokens)
+    }
+
+    /// Define the role definition `key`, e.g. `g` or `g2`, whose links have `arity` fields:
+    /// 2 for a user and a role, 3 to add a domain.
+    pub fn role(self, key: &str, arity: usize) -> Self {
+        if arity < 2 {
+            let reason = format!("a role link has at least 2 fields, got {}", arity);
+            return self.fail("g", key, reason);
+        }
+        let value = vec!["_"; arity].join(", ");
+        self.add("g", key, value)
+    }
+
+    /// Define the policy effect, e.g. `some(where (p.eft == allow))`.
+    pub fn effect(self, effect: &str) -> Self {
+        self.add("e", "e", effect.trim().to_string())
+    }
+
+    /// Define the matcher, e.g. `r.sub == p.sub && r.obj == p.obj && r.act == p.act`.
+    pub fn matcher(self, matcher: &str) -> Self {
+        self.add("m", "m", matcher.trim().to_string())
+    }
+
+    /// Build the model, it is the same as the one parsed from the equivalent `.conf` file and is
+    /// validated the same way.
+    pub fn build(self) -> Result<Model, Error> {
+        if let Some(err) = self.error {
+            return Err(err);
+        }
+
+        let mut model = Model::new();
+        for (sec, key, value) in &self.defs {
+            model.add_def(sec, key, value)?;
+        }
+        model.validate()?;
+        Ok(model)
+    }
+
+    fn add_tokens(self, sec: &'static str, key: &str, tokens: &[&str]) -> Self {
+        if tokens.is_empty() {
+            return self.fail(sec, key, "no field is defined".to_string());
+        }
+        for (i, token) in tokens.iter().enumerate() {
+            if !is_identifier(token) {
+                return self.fail(sec, key, format!("`{}` is not a valid field name", token));
+            }
+            if tokens[..i].contains(token) {
+                return self.fail(sec, key, format!("the field `{}` is defined twice", token));
+            }
+        }
+        self.add(sec, key, tokens.join(", "))
+    }
+
+    fn add(mut self, sec: &'static str, key: &str, value: String) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        if !is_key_of(sec, key) {
+            let reason = format!("the key of a `{}` definition is `{}` followed by a number", sec, sec);
+            return self.fail(sec, key, reason);
+        }
+        if value.is_empty() {
+            return self.fail(sec, key, "the definition is empty".to_string());
+        }
+        if self.defs.iter().any(|(_, k, _)| k == key) {
+            return self.fail(sec, key, "the definition is given twice".to_string());
+        }
+
+        self.defs.push((sec, key.to_string(), value));
+        self
+    }
+
+    /// Record the first error, it is returned by `build`.
+    fn fail(mut self, sec: &str, key: &str, reason: String) -> Self {
+        if self.error.is_none() {
+            self.error = Some(model_error(sec, key, ModelErrorKind::InvalidDefinition(reason)));
+        }
+        self
+    }
+}
+
+/// Determine whether `key` names a definition of the section `sec`, e.g. `p` or `p2` for `p`.
+fn is_key_of(sec: &str, key: &str) -> bool {
+    key.starts_with(sec) && key[sec.len()..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn is_identifier(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ModelError;
+
+    #[test]
+    fn test_build_model() {
+        let model = Model::builder()
+            .request(&["sub", "dom", "obj", "act"])
+            .policy(&["sub", "dom", "obj", "act"])
+            .role("g", 3)
+            .effect("some(where (p.eft == allow))")
+            .matcher("g(r.sub, p.sub, r.dom) && r.dom == p.dom && r.obj == p.obj && r.act == p.act")
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            model,
+            Model::from_file("examples/rbac_with_domains_model.conf").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_build_model_errors() {
+        let builder = || {
+            Model::builder()
+                .request(&["sub", "obj", "act"])
+                .effect("some(where (p.eft == allow))")
+                .matcher("r.sub == p.sub && r.obj == p.obj && r.act == p.act")
+        };
+
+        let invalid_definition = |result: Result<Model, Error>, expected: &str| {
+            assert!(matches!(
+                result,
+                Err(Error::Model(ModelError { key, kind: ModelErrorKind::InvalidDefinition(_), .. })) if key == expected
+            ))
+        };
+
+        let err = builder().build().unwrap_err();
+        assert!(matches!(
+            err,
+            Error::Model(ModelError {
+                kind: ModelErrorKind::MissingDefinition,
+                ..
+            })
+        ));
+        assert_eq!(
+            err.to_string(),
+            "Invalid model: [policy_definition] p: missing definition"
+        );
+        invalid_definition(builder().policy(&[]).build(), "p");
+        invalid_definition(builder().policy(&["sub", "obj", "sub"]).build(), "p");
+        invalid_definition(builder().policy(&["sub", "p.obj"]).build(), "p");
+        invalid_definition(builder().named_policy("q", &["sub"]).policy(&["sub"]).build(), "q");
+        invalid_definition(builder().policy(&["sub"]).role("g2", 1).build(), "g2");
+        invalid_definition(builder().policy(&["sub"]).role("g", 2).role("g", 3).build(), "g");
+        invalid_definition(builder().policy(&["sub"]).effect("").build(), "e");
+
+        assert_eq!(builder().policy(&["sub", "obj", "act"]).build().is_ok(), true);
+    }
+}
@@ -1,21 +1,59 @@
-use std::collections::HashMap;
-
-use crate::assertion::Assertion;
-use crate::error::Error;
-use crate::model::Model;
+use crate::error::{Error, PolicyError};
+use crate::model::{Model, RuleSet};
 use crate::rbac::RoleManager;
+use crate::util::to_strings;
 
 impl Model {
-    /// Initialize the roles in RBAC.
-    pub fn build_role_links<RM: RoleManager + Send + 'static>(&mut self, role_manager: &mut RM) -> Result<(), Error> {
+    /// Initialize the roles in RBAC for the role definition `ptype`.
+    pub fn build_role_links<RM: RoleManager + Send + 'static>(
+        &mut self,
+        ptype: &str,
+        role_manager: &mut RM,
+    ) -> Result<(), Error> {
         if let Some(g) = self.data.get_mut("g") {
-            for assertion in g.values_mut() {
+            if let Some(assertion) = g.get_mut(ptype) {
                 assertion.build_role_links(role_manager)?;
             }
         }
         Ok(())
     }
 
+    /// Add the role links of the grouping `rules` of the role definition `ptype` to `role_manager`.
+    pub fn add_role_links<RM: RoleManager + Send + 'static>(
+        &self,
+        ptype: &str,
+        role_manager: &mut RM,
+        rules: &[Vec<String>],
+    ) -> Result<(), Error> {
+        if let Some(assertion) = self.data.get("g").and_then(|g| g.get(ptype)) {
+            assertion.add_role_links(role_manager, rules)?;
+        }
+        Ok(())
+    }
+
+    /// Delete the role links of the grouping `rules` of the role definition `ptype` from `role_manager`.
+    pub fn delete_role_links<RM: RoleManager + Send + 'static>(
+        &self,
+        ptype: &str,
+        role_manager: &mut RM,
+        rules: &[Vec<String>],
+    ) -> Result<(), Error> {
+        if let Some(assertion) = self.data.get("g").and_then(|g| g.get(ptype)) {
+            assertion.delete_role_links(role_manager, rules)?;
+        }
+        Ok(())
+    }
+
+    /// Get the policy types defined in a section, e.g. `p`, `p2` or `g`, `g2`, in sorted order.
+    pub fn get_ptypes(&self, sec: &str) -> Vec<String> {
+        let mut ptypes: Vec<String> = match self.data.get(sec) {
+            Some(sec_map) => sec_map.keys().cloned().collect(),
+            None => Vec::new(),
+        };
+        ptypes.sort();
+        ptypes
+    }
+
     /// Clear all the current policies.
     pub fn clear_policy(&mut self) {
         if let Some(p) = self.data.get_mut("p") {
@@ -34,12 +72,22 @@ impl Model {
     pub fn get_policy(&self, section: &str, ptype: &str) -> Option<Vec<Vec<String>>> {
         if let Some(section_map) = self.data.get(section) {
             if let Some(assertion) = section_map.get(ptype) {
-                return Some(assertion.policy.clone());
+                return Some(assertion.policy().to_vec());
             }
         }
         None
     }
 
+    /// Index the rules of the policy type `ptype` by the value of the field `field_index`.
+    ///
+    /// Filtered queries on an indexed field only look at the rules with the requested value instead of
+    /// the whole policy. The index is kept up to date by every change of the policy.
+    pub fn add_field_index(&mut self, sec: &str, ptype: &str, field_index: usize) {
+        if let Some(assertion) = self.data.get_mut(sec).and_then(|sec_map| sec_map.get_mut(ptype)) {
+            assertion.policy.add_field_index(field_index);
+        }
+    }
+
     /// Get rules based on field filters from a policy.
     pub fn get_filtered_policy(
         &self,
@@ -48,24 +96,13 @@ impl Model {
         field_index: usize,
         field_values: &[&str],
     ) -> Option<Vec<Vec<String>>> {
-        let mut res: Vec<Vec<String>> = Vec::new();
-        if let Some(sec_map) = self.data.get(sec) {
-            if let Some(assertion) = sec_map.get(ptype) {
-                for rules in &assertion.policy {
-                    let mut matched = true;
-                    let i_rules = &rules[field_index..];
-                    for (rule, field_value) in i_rules.iter().zip(field_values) {
-                        if !field_value.is_empty() && rule != field_value {
-                            matched = false;
-                            break;
-                        }
-                    }
-                    if matched {
-                        res.push(rules.clone());
-                    }
-                }
-            }
-        }
+        let res: Vec<Vec<String>> = match self.get_assertion(sec, ptype) {
+            Some(assertion) => filtered_rules(&assertion.policy, field_index, field_values)
+                .into_iter()
+                .cloned()
+                .collect(),
+            None => Vec::new(),
+        };
         if res.is_empty() {
             None
         } else {
@@ -73,57 +110,213 @@ impl Model {
         }
     }
 
+    /// Get the positions in `get_rules` of the rules of the policy type `ptype` whose field `field_index`
+    /// is `value`, in increasing order.
+    ///
+    /// Returns None if the field is not indexed, see `add_field_index`.
+    pub(crate) fn find_rule_positions(
+        &self,
+        sec: &str,
+        ptype: &str,
+        field_index: usize,
+        value: &str,
+    ) -> Option<&[usize]> {
+        self.get_assertion(sec, ptype)?.policy.find(field_index, value)
+    }
+
+    /// Check that `rule` matches the definition of the policy type `ptype`.
+    ///
+    /// A policy rule must have one value per token of its policy definition, a grouping rule at least one
+    /// value per `_` of its role definition, further values are custom data. Values cannot contain line
+    /// breaks, a policy text holds one rule per line. `line` is the line number the rule was read from, if
+    /// any, and is only used in the error.
+    pub fn check_rule<S: AsRef<str>>(
+        &self,
+        sec: &str,
+        ptype: &str,
+        rule: &[S],
+        line: Option<usize>,
+    ) -> Result<(), Error> {
+        let assertion = match self.data.get(sec).and_then(|sec_map| sec_map.get(ptype)) {
+            Some(assertion) if sec == "p" || sec == "g" => assertion,
+            _ => {
+                return Err(rule_error(
+                    ptype,
+                    rule,
+                    line,
+                    "the policy type is not defined in the model",
+                ))
+            }
+        };
+
+        if rule.iter().any(|value| value.as_ref().contains(['\n', '\r'])) {
+            return Err(rule_error(ptype, rule, line, "a value contains a line break"));
+        }
+
+        if sec == "p" {
+            if rule.len() != assertion.tokens.len() {
+                let reason = format!(
+                    "expected {} fields ({}), found {}",
+                    assertion.tokens.len(),
+                    assertion.value,
+                    rule.len()
+                );
+                return Err(rule_error(ptype, rule, line, &reason));
+            }
+        } else {
+            let count = assertion.value.matches('_').count();
+            if rule.len() < count {
+                let reason = format!(
+                    "expected at least {} fields ({}), found {}",
+                    count,
+                    assertion.value,
+                    rule.len()
+                );
+                return Err(rule_error(ptype, rule, line, &reason));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Determine whether a model has the specified policy rule.
     pub fn has_policy(&self, sec: &str, ptype: &str, rule: &[&str]) -> bool {
-        if let Some(sec_map) = self.data.get(sec) {
-            if let Some(assertion) = sec_map.get(ptype) {
-                for a_rule in &assertion.policy {
-                    if a_rule.as_slice() == rule {
-                        return true;
-                    }
-                }
-            }
+        match self.get_assertion(sec, ptype) {
+            Some(assertion) => assertion.policy.contains(rule),
+            None => false,
         }
-        false
     }
 
     /// Add a policy rule to the model.
-    pub fn add_policy(&mut self, sec: &str, ptype: &str, rule: &[&str]) -> bool {
-        if !self.has_policy(sec, ptype, rule) {
-            if !self.data.contains_key(sec) {
-                let sec_map: HashMap<String, Assertion> = HashMap::new();
-                self.data.insert(sec.to_string(), sec_map);
+    ///
+    /// Returns an error if the rule does not match the definition of `ptype`, see `check_rule`.
+    pub fn add_policy(&mut self, sec: &str, ptype: &str, rule: &[&str]) -> Result<bool, Error> {
+        self.check_rule(sec, ptype, rule, None)?;
+
+        let rule = to_strings(rule);
+        let assertion = self.data.get_mut(sec).unwrap().get_mut(ptype).unwrap();
+        Ok(assertion.policy.insert(rule))
+    }
+
+    /// Add several policy rules to the model.
+    ///
+    /// The rules are added all or none: if any of them already exists or is repeated, the model is
+    /// left unchanged and false is returned.
+    pub fn add_policies(&mut self, sec: &str, ptype: &str, rules: &[Vec<&str>]) -> Result<bool, Error> {
+        for (i, rule) in rules.iter().enumerate() {
+            self.check_rule(sec, ptype, rule, None)?;
+            if self.has_policy(sec, ptype, rule) || rules[..i].contains(rule) {
+                return Ok(false);
             }
+        }
+        for rule in rules {
+            self.add_policy(sec, ptype, rule)?;
+        }
+        Ok(true)
+    }
+
+    /// Removes a policy rule from the model.
+    pub fn remove_policy(&mut self, sec: &str, ptype: &str, rule: &[&str]) -> bool {
+        match self.data.get_mut(sec).and_then(|sec_map| sec_map.get_mut(ptype)) {
+            Some(assertion) => assertion.policy.remove(rule),
+            None => false,
+        }
+    }
 
-            let sec_map = self.data.get_mut(sec).unwrap();
-            if !sec_map.contains_key(ptype) {
-                let assertion = Assertion::new();
-                sec_map.insert(ptype.to_string(), assertion);
+    /// Remove several policy rules from the model.
+    ///
+    /// The rules are removed all or none: if any of them does not exist or is repeated, the model is
+    /// left unchanged and false is returned.
+    pub fn remove_policies(&mut self, sec: &str, ptype: &str, rules: &[Vec<&str>]) -> bool {
+        for (i, rule) in rules.iter().enumerate() {
+            if !self.has_policy(sec, ptype, rule) || rules[..i].contains(rule) {
+                return false;
             }
+        }
+        if let Some(assertion) = self.data.get_mut(sec).and_then(|sec_map| sec_map.get_mut(ptype)) {
+            assertion.policy.remove_all(rules);
+        }
+        true
+    }
+
+    /// Replace the policy rule `old_rule` by `new_rule`, keeping its position in the policy.
+    ///
+    /// Returns false and leaves the model unchanged if `old_rule` does not exist or `new_rule` already
+    /// exists.
+    pub fn update_policy(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rule: &[&str],
+        new_rule: &[&str],
+    ) -> Result<bool, Error> {
+        self.update_policies(sec, ptype, &[old_rule.to_vec()], &[new_rule.to_vec()])
+    }
 
-            let rule: Vec<String> = rule.iter().map(|s| s.to_string()).collect();
+    /// Replace each rule of `old_rules` by the rule at the same index in `new_rules`, keeping their
+    /// positions in the policy.
+    ///
+    /// The rules are replaced all or none: if any old rule does not exist, or a new rule already exists
+    /// and is not replaced itself, the model is left unchanged and false is returned.
+    pub fn update_policies(
+        &mut self,
+        sec: &str,
+        ptype: &str,
+        old_rules: &[Vec<&str>],
+        new_rules: &[Vec<&str>],
+    ) -> Result<bool, Error> {
+        for rule in new_rules {
+            self.check_rule(sec, ptype, rule, None)?;
+        }
 
-            let assertion = sec_map.get_mut(ptype).unwrap();
-            assertion.policy.push(rule);
+        let indexes = match self.find_policy_updates(sec, ptype, old_rules, new_rules) {
+            Some(indexes) => indexes,
+            None => return Ok(false),
+        };
 
-            true
-        } else {
-            false
+        let assertion = self.data.get_mut(sec).unwrap().get_mut(ptype).unwrap();
+        for (index, new_rule) in indexes.into_iter().zip(new_rules) {
+            assertion
+                .policy
+                .replace(index, to_strings(new_rule));
         }
+        Ok(true)
     }
 
-    /// Removes a policy rule from the model.
-    pub fn remove_policy(&mut self, sec: &str, ptype: &str, rule: &[&str]) -> bool {
-        if let Some(sec_map) = self.data.get_mut(sec) {
-            if let Some(assertion) = sec_map.get_mut(ptype) {
-                let index = assertion.policy.iter().position(|x| *x == rule);
-                if let Some(index) = index {
-                    assertion.policy.remove(index);
-                    return true;
-                }
+    /// Get the positions of `old_rules` in the policy if they can be replaced by `new_rules`, see
+    /// `update_policies`.
+    pub(crate) fn find_policy_updates(
+        &self,
+        sec: &str,
+        ptype: &str,
+        old_rules: &[Vec<&str>],
+        new_rules: &[Vec<&str>],
+    ) -> Option<Vec<usize>> {
+        if old_rules.len() != new_rules.len() {
+            return None;
+        }
+
+        let assertion = self.data.get(sec)?.get(ptype)?;
+
+        let mut indexes: Vec<usize> = Vec::new();
+        for old_rule in old_rules {
+            match assertion.policy.position(old_rule) {
+                Some(index) if !indexes.contains(&index) => indexes.push(index),
+                _ => return None,
+            }
+        }
+
+        for (i, new_rule) in new_rules.iter().enumerate() {
+            let exists = match assertion.policy.position(new_rule) {
+                Some(index) => !indexes.contains(&index),
+                None => false,
+            };
+            if exists || new_rules[..i].contains(new_rule) {
+                return None;
             }
         }
-        false
+
+        Some(indexes)
     }
 
     /// Removes policy rules based on field filters from the model.
@@ -134,29 +327,12 @@ impl Model {
         field_index: usize,
         field_values: &[&str],
     ) -> bool {
-        let mut res: bool = false;
-        if let Some(sec_map) = self.data.get_mut(sec) {
-            if let Some(assertion) = sec_map.get_mut(ptype) {
-                let mut tmp: Vec<Vec<String>> = Vec::new();
-                for rules in &assertion.policy {
-                    let mut matched = true;
-                    let i_rules = &rules[field_index..];
-                    for (rule, field_value) in i_rules.iter().zip(field_values) {
-                        if !field_value.is_empty() && rule != field_value {
-                            matched = false;
-                            break;
-                        }
-                    }
-                    if matched {
-                        res = true;
-                    } else {
-                        tmp.push(rules.clone());
-                    }
-                }
-                assertion.policy = tmp;
-            }
+        match self.data.get_mut(sec).and_then(|sec_map| sec_map.get_mut(ptype)) {
+            Some(assertion) => assertion
+                .policy
+                .retain(|rule| !rule_matches(rule, field_index, field_values)),
+            None => false,
         }
-        res
     }
 
     /// Get all values for a field for all rules in a policy, duplicated values are removed.
@@ -165,7 +341,7 @@ impl Model {
 
         if let Some(sec_map) = self.data.get(section) {
             if let Some(assertion) = sec_map.get(ptype) {
-                for rules in &assertion.policy {
+                for rules in assertion.policy() {
                     if let Some(rule) = rules.get(field_index as usize) {
                         values.push(rule.to_string());
                     }
@@ -177,4 +353,61 @@ impl Model {
         values.dedup();
         values
     }
+
+    /// Get all values for a field for all rules in all policies of a section, duplicated values are removed.
+    pub fn get_values_for_field_in_policy_all_types(&self, section: &str, field_index: i32) -> Vec<String> {
+        let mut values: Vec<String> = Vec::new();
+
+        if let Some(sec_map) = self.data.get(section) {
+            for ptype in sec_map.keys() {
+                values.extend(self.get_values_for_field_in_policy(section, ptype, field_index));
+            }
+        }
+
+        values.sort();
+        values.dedup();
+        values
+    }
+}
+
+fn rule_error<S: AsRef<str>>(ptype: &str, rule: &[S], line: Option<usize>, reason: &str) -> Error {
+    Error::Policy(PolicyError::new(ptype, rule, line, reason))
+}
+
+/// Get the rules of `rules` that match the `field_values` starting at `field_index`, in order.
+///
+/// The first non-empty field value that is indexed selects the candidate rules, otherwise every rule
+/// is checked.
+fn filtered_rules<'a>(rules: &'a RuleSet, field_index: usize, field_values: &[&str]) -> Vec<&'a Vec<String>> {
+    let indexed = field_values
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| !value.is_empty())
+        .find_map(|(i, value)| rules.find(field_index + i, value));
+
+    match indexed {
+        Some(positions) => positions
+            .iter()
+            .filter_map(|&position| rules.get(position))
+            .filter(|rule| rule_matches(rule, field_index, field_values))
+            .collect(),
+        None => rules
+            .rules()
+            .iter()
+            .filter(|rule| rule_matches(rule, field_index, field_values))
+            .collect(),
+    }
+}
+
+/// Determine whether `rule` matches the `field_values` starting at `field_index`.
+///
+/// An empty field value matches any value, but the field must exist in the rule.
+fn rule_matches(rule: &[String], field_index: usize, field_values: &[&str]) -> bool {
+    field_values
+        .iter()
+        .enumerate()
+        .all(|(i, value)| match rule.get(field_index + i) {
+            Some(field) => value.is_empty() || field == value,
+            None => false,
+        })
 }
@@ -0,0 +1,266 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash, Hasher};
+
+/// The rules of a policy type in insertion order, indexed for fast lookups.
+///
+/// Every rule is unique and its position is kept under the hash of the rule, so membership is tested
+/// in constant time without building a key. Fields can also be indexed by value, e.g. the subject of
+/// `p = sub, obj, act`, to find the rules with a given value without scanning the whole policy.
+#[derive(Debug, Default)]
+pub(crate) struct RuleSet {
+    rules: Vec<Vec<String>>,
+    hasher: RandomState,
+    // rule hash -> positions of the rules with that hash
+    positions: HashMap<u64, Vec<usize>>,
+    // field index -> field value -> positions of the rules with that value, in increasing order
+    field_indexes: HashMap<usize, HashMap<String, Vec<usize>>>,
+}
+
+impl RuleSet {
+    pub fn new() -> Self {
+        RuleSet::default()
+    }
+
+    pub fn rules(&self) -> &[Vec<String>] {
+        &self.rules
+    }
+
+    pub fn get(&self, position: usize) -> Option<&Vec<String>> {
+        self.rules.get(position)
+    }
+
+    pub fn position<S: AsRef<str>>(&self, rule: &[S]) -> Option<usize> {
+        let candidates = self.positions.get(&self.hash(rule))?;
+        candidates.iter().cloned().find(|&position| {
+            let stored = &self.rules[position];
+            stored.len() == rule.len() && stored.iter().zip(rule).all(|(a, b)| a == b.as_ref())
+        })
+    }
+
+    pub fn contains<S: AsRef<str>>(&self, rule: &[S]) -> bool {
+        self.position(rule).is_some()
+    }
+
+    /// Add `rule` at the end, returns false if it already exists.
+    pub fn insert(&mut self, rule: Vec<String>) -> bool {
+        if self.contains(&rule) {
+            return false;
+        }
+
+        let position = self.rules.len();
+        for (field, index) in self.field_indexes.iter_mut() {
+            if let Some(value) = rule.get(*field) {
+                index.entry(value.clone()).or_default().push(position);
+            }
+        }
+        let hash = self.hash(&rule);
+        self.positions.entry(hash).or_default().push(position);
+        self.rules.push(rule);
+        true
+    }
+
+    /// Replace the rule at `position` by `rule`, which must not exist in the set.
+    pub fn replace(&mut self, position: usize, rule: Vec<String>) {
+        let old_hash = self.hash(&self.rules[position]);
+        let new_hash = self.hash(&rule);
+        let old_rule = std::mem::replace(&mut self.rules[position], rule);
+        remove_position(&mut self.positions, &old_hash, position);
+        self.positions.entry(new_hash).or_default().push(position);
+
+        for (field, index) in self.field_indexes.iter_mut() {
+            if let Some(value) = old_rule.get(*field) {
+                remove_position(index, value, position);
+            }
+            if let Some(value) = self.rules[position].get(*field) {
+                let positions = index.entry(value.clone()).or_default();
+                if let Err(i) = positions.binary_search(&position) {
+                    positions.insert(i, position);
+                }
+            }
+        }
+    }
+
+    /// Remove `rule`, returns false if it does not exist.
+    pub fn remove<S: AsRef<str>>(&mut self, rule: &[S]) -> bool {
+        match self.position(rule) {
+            Some(position) => {
+                let mut removed = vec![false; self.rules.len()];
+                removed[position] = true;
+                self.remove_positions(&removed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove every rule of `rules` in a single pass, returns the number of removed rules.
+    pub fn remove_all<S: AsRef<str>>(&mut self, rules: &[Vec<S>]) -> usize {
+        let mut removed = vec![false; self.rules.len()];
+        for rule in rules {
+            if let Some(position) = self.position(rule) {
+                removed[position] = true;
+            }
+        }
+        self.remove_positions(&removed)
+    }
+
+    /// Keep only the rules for which `f` returns true, returns whether any rule was removed.
+    pub fn retain<F: FnMut(&Vec<String>) -> bool>(&mut self, mut f: F) -> bool {
+        let removed: Vec<bool> = self.rules.iter().map(|rule| !f(rule)).collect();
+        self.remove_positions(&removed) > 0
+    }
+
+    /// Remove every rule, the indexed fields stay indexed.
+    pub fn clear(&mut self) {
+        self.rules.clear();
+        self.positions.clear();
+        for index in self.field_indexes.values_mut() {
+            index.clear();
+        }
+    }
+
+    /// Index the rules by the value of `field`, see `find`.
+    pub fn add_field_index(&mut self, field: usize) {
+        if self.field_indexes.contains_key(&field) {
+            return;
+        }
+
+        let mut index: HashMap<String, Vec<usize>> = HashMap::new();
+        for (position, rule) in self.rules.iter().enumerate() {
+            if let Some(value) = rule.get(field) {
+                index.entry(value.clone()).or_default().push(position);
+            }
+        }
+        self.field_indexes.insert(field, index);
+    }
+
+    /// Get the positions of the rules whose `field` is `value`, in increasing order.
+    ///
+    /// Returns None if `field` is not indexed.
+    pub fn find(&self, field: usize, value: &str) -> Option<&[usize]> {
+        let index = self.field_indexes.get(&field)?;
+        Some(index.get(value).map_or(&[], |positions| positions.as_slice()))
+    }
+
+    fn hash<S: AsRef<str>>(&self, rule: &[S]) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        rule.len().hash(&mut hasher);
+        for value in rule {
+            value.as_ref().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Remove the rules whose position is flagged in `removed` and shift the positions of the rules
+    /// after them, returns the number of removed rules.
+    fn remove_positions(&mut self, removed: &[bool]) -> usize {
+        let mut new_positions = Vec::with_capacity(removed.len());
+        let mut count = 0;
+        for &is_removed in removed {
+            if is_removed {
+                new_positions.push(None);
+                count += 1;
+            } else {
+                new_positions.push(Some(new_positions.len() - count));
+            }
+        }
+        if count == 0 {
+            return 0;
+        }
+
+        let mut position = 0;
+        self.rules.retain(|_| {
+            position += 1;
+            !removed[position - 1]
+        });
+        shift_positions(&mut self.positions, &new_positions);
+        for index in self.field_indexes.values_mut() {
+            shift_positions(index, &new_positions);
+        }
+        count
+    }
+}
+
+impl PartialEq for RuleSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules
+    }
+}
+
+fn remove_position<K, Q>(map: &mut HashMap<K, Vec<usize>>, key: &Q, position: usize)
+where
+    K: std::borrow::Borrow<Q> + Eq + Hash,
+    Q: Eq + Hash + ?Sized,
+{
+    if let Some(positions) = map.get_mut(key) {
+        positions.retain(|&p| p != position);
+        if positions.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+/// Move the positions of `map` to `new_positions`, dropping the removed ones.
+fn shift_positions<K: Eq + Hash>(map: &mut HashMap<K, Vec<usize>>, new_positions: &[Option<usize>]) {
+    map.retain(|_, positions| {
+        positions.retain(|&p| new_positions[p].is_some());
+        for p in positions.iter_mut() {
+            *p = new_positions[*p].unwrap();
+        }
+        !positions.is_empty()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::to_strings;
+
+    #[test]
+    fn test_rule_set() {
+        let mut rules = RuleSet::new();
+        rules.add_field_index(0);
+
+        assert_eq!(rules.insert(to_strings(&["alice", "data1", "read"])), true);
+        assert_eq!(rules.insert(to_strings(&["bob", "data2", "write"])), true);
+        assert_eq!(rules.insert(to_strings(&["alice", "data2", "read"])), true);
+        assert_eq!(rules.insert(to_strings(&["alice", "data1", "read"])), false);
+
+        assert_eq!(rules.contains(&["bob", "data2", "write"]), true);
+        assert_eq!(rules.contains(&["bob", "data2", "read"]), false);
+        assert_eq!(rules.find(0, "alice"), Some(&[0, 2][..]));
+        assert_eq!(rules.find(0, "eve"), Some(&[][..]));
+        assert_eq!(rules.find(1, "data1"), None);
+
+        assert_eq!(rules.remove(&["alice", "data1", "read"]), true);
+        assert_eq!(rules.remove(&["alice", "data1", "read"]), false);
+        assert_eq!(rules.position(&["alice", "data2", "read"]), Some(1));
+        assert_eq!(rules.find(0, "alice"), Some(&[1][..]));
+
+        rules.replace(0, to_strings(&["alice", "data3", "read"]));
+        assert_eq!(rules.find(0, "alice"), Some(&[0, 1][..]));
+        assert_eq!(rules.find(0, "bob"), Some(&[][..]));
+
+        rules.add_field_index(1);
+        assert_eq!(rules.find(1, "data2"), Some(&[1][..]));
+        assert_eq!(rules.retain(|r| r[1] != "data2"), true);
+        assert_eq!(rules.rules(), &[to_strings(&["alice", "data3", "read"])][..]);
+        assert_eq!(rules.find(1, "data2"), Some(&[][..]));
+
+        rules.insert(to_strings(&["bob", "data1", "read"]));
+        rules.insert(to_strings(&["alice", "data2", "write"]));
+        assert_eq!(
+            rules.remove_all(&[vec!["alice", "data3", "read"], vec!["bob", "data1", "read"]]),
+            2
+        );
+        assert_eq!(rules.rules(), &[to_strings(&["alice", "data2", "write"])][..]);
+        assert_eq!(rules.position(&["alice", "data2", "write"]), Some(0));
+        assert_eq!(rules.find(0, "alice"), Some(&[0][..]));
+        assert_eq!(rules.find(1, "data2"), Some(&[0][..]));
+
+        rules.clear();
+        assert_eq!(rules.rules().is_empty(), true);
+        assert_eq!(rules.find(0, "alice"), Some(&[][..]));
+    }
+}
@@ -1,7 +1,10 @@
 use crate::error::Error;
 use crate::model::Model;
+use crate::util::{format_csv_line, parse_csv_line};
 
 pub mod file_adapter;
+pub mod memory_adapter;
+pub mod string_adapter;
 
 pub trait Adapter {
     fn load_policy(&self, model: &mut Model) -> Result<(), Error>;
@@ -15,6 +18,75 @@ pub trait Adapter {
         field_index: i32,
         field_values: Vec<String>,
     ) -> Result<(), Error>;
+
+    /// Add several rules to the storage, all or none.
+    ///
+    /// The default implementation adds the rules one by one and removes the added ones if a rule cannot
+    /// be added, adapters backed by a store that supports transactions should override it.
+    fn add_policies(&self, sec: &str, ptype: &str, rules: Vec<Vec<String>>) -> Result<(), Error> {
+        for (i, rule) in rules.iter().enumerate() {
+            if let Err(err) = self.add_policy(sec, ptype, rule.clone()) {
+                for rule in rules[..i].iter().rev() {
+                    self.remove_policy(sec, ptype, rule.clone())?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Replace the rule `old_rule` by `new_rule` in the storage.
+    ///
+    /// The default implementation removes the old rule and adds the new one, adding the old rule back
+    /// if the new one cannot be added. Adapters should override it to change the rule in a single
+    /// operation and keep its position.
+    fn update_policy(&self, sec: &str, ptype: &str, old_rule: Vec<String>, new_rule: Vec<String>) -> Result<(), Error> {
+        self.remove_policy(sec, ptype, old_rule.clone())?;
+        if let Err(err) = self.add_policy(sec, ptype, new_rule) {
+            self.add_policy(sec, ptype, old_rule)?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Replace each rule of `old_rules` by the rule at the same index in `new_rules`, all or none, see
+    /// `update_policy`.
+    ///
+    /// The default implementation updates the rules one by one and reverts the applied updates if a
+    /// rule cannot be updated.
+    fn update_policies(
+        &self,
+        sec: &str,
+        ptype: &str,
+        old_rules: Vec<Vec<String>>,
+        new_rules: Vec<Vec<String>>,
+    ) -> Result<(), Error> {
+        for (i, (old_rule, new_rule)) in old_rules.iter().zip(&new_rules).enumerate() {
+            if let Err(err) = self.update_policy(sec, ptype, old_rule.clone(), new_rule.clone()) {
+                for (old_rule, new_rule) in old_rules[..i].iter().zip(&new_rules).rev() {
+                    self.update_policy(sec, ptype, new_rule.clone(), old_rule.clone())?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove several rules from the storage, all or none, see `add_policies`.
+    ///
+    /// The rules removed by the default implementation before a failure are added back at the end of
+    /// the storage.
+    fn remove_policies(&self, sec: &str, ptype: &str, rules: Vec<Vec<String>>) -> Result<(), Error> {
+        for (i, rule) in rules.iter().enumerate() {
+            if let Err(err) = self.remove_policy(sec, ptype, rule.clone()) {
+                for rule in &rules[..i] {
+                    self.add_policy(sec, ptype, rule.clone())?;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
 }
 
 pub struct Filter {
@@ -29,6 +101,67 @@ impl Filter {
             g: Vec::new(),
         }
     }
+
+    /// Determine whether the filter keeps `rule` of the section `sec`.
+    ///
+    /// The filter values of the section are compared with the fields of the rule in order, an empty
+    /// value matches any field. A rule of another section than `p` or `g` is always kept.
+    pub fn keeps<S: AsRef<str>>(&self, sec: &str, rule: &[S]) -> bool {
+        let values = match sec {
+            "p" => &self.p,
+            "g" => &self.g,
+            _ => return true,
+        };
+        if rule.len() < values.len() {
+            return false;
+        }
+
+        values
+            .iter()
+            .zip(rule)
+            .all(|(value, field)| value.is_empty() || value.trim() == field.as_ref().trim())
+    }
+}
+
+/// Load the policy rules of a CSV `text` into `model`, one rule per line starting with its policy type.
+///
+/// Empty lines and lines starting with `#` are skipped, as are the rules the `filter` does not keep.
+/// The errors report the line number of the rule.
+pub fn load_policy_text(model: &mut Model, text: &str, filter: Option<&Filter>) -> Result<(), Error> {
+    for (i, line) in text.lines().enumerate() {
+        if filter.is_some_and(|filter| filter_line(line, filter)) {
+            continue;
+        }
+        model.load_numbered_policy_line(line, i + 1)?;
+    }
+    Ok(())
+}
+
+/// Format the policy rules of `model` as a CSV text, see `load_policy_text`.
+pub fn policy_text(model: &Model) -> String {
+    let mut text = String::new();
+
+    for sec in &["p", "g"] {
+        for ptype in model.get_ptypes(sec) {
+            for rule in model.get_policy(sec, &ptype).unwrap_or_default() {
+                let mut values = vec![ptype.clone()];
+                values.extend(rule);
+                text.push_str(&format_csv_line(&values));
+                text.push('\n');
+            }
+        }
+    }
+
+    text
+}
+
+/// Determine whether the line of a policy text is skipped by `filter`.
+fn filter_line(line: &str, filter: &Filter) -> bool {
+    match parse_csv_line(line) {
+        Some(p) if !p.is_empty() => !filter.keeps(p[0].get(..1).unwrap_or_default(), &p[1..]),
+        // keep the line so that loading it reports the error
+        _ => false,
+    }
 }
 
 pub trait FilteredAdapter: Adapter {
@@ -40,3 +173,93 @@ pub trait Watcher {
     fn set_update_callback<F: FnMut(&str) + 'static>(&mut self, callback: F) -> Result<(), Error>;
     fn update(&self) -> Result<(), Error>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AdapterError;
+    use crate::persist::memory_adapter::MemoryAdapter;
+    use crate::util::to_strings;
+
+    // A memory adapter that cannot add the rules of `error` nor remove the rules of `locked`.
+    #[derive(Default)]
+    struct FailingAdapter(MemoryAdapter);
+
+    impl Adapter for FailingAdapter {
+        fn load_policy(&self, model: &mut Model) -> Result<(), Error> {
+            self.0.load_policy(model)
+        }
+
+        fn save_policy(&self, model: &mut Model) -> Result<(), Error> {
+            self.0.save_policy(model)
+        }
+
+        fn add_policy(&self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
+            if rule[0] == "error" {
+                return Err(AdapterError::new("cannot add the rule").into());
+            }
+            self.0.add_policy(sec, ptype, rule)
+        }
+
+        fn remove_policy(&self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
+            if rule[0] == "locked" {
+                return Err(AdapterError::new("cannot remove the rule").into());
+            }
+            self.0.remove_policy(sec, ptype, rule)
+        }
+
+        fn remove_filtered_policy(
+            &self,
+            sec: &str,
+            ptype: &str,
+            field_index: i32,
+            field_values: Vec<String>,
+        ) -> Result<(), Error> {
+            self.0.remove_filtered_policy(sec, ptype, field_index, field_values)
+        }
+    }
+
+    fn sorted_policy(adapter: &FailingAdapter) -> Vec<Vec<String>> {
+        let mut policy = adapter.0.get_policy("p");
+        policy.sort();
+        policy
+    }
+
+    #[test]
+    fn test_batch_rollback() {
+        let adapter = FailingAdapter::default();
+        adapter.add_policy("p", "p", to_strings(&["alice", "data1", "read"])).unwrap();
+        adapter
+            .add_policy("p", "p", to_strings(&["locked", "data1", "read"]))
+            .unwrap();
+        let policy = sorted_policy(&adapter);
+
+        let rules = vec![to_strings(&["bob", "data2", "read"]), to_strings(&["error", "data2", "read"])];
+        assert!(matches!(adapter.add_policies("p", "p", rules), Err(Error::Adapter(_))));
+        assert_eq!(sorted_policy(&adapter), policy);
+
+        let rules = vec![to_strings(&["alice", "data1", "read"]), to_strings(&["locked", "data1", "read"])];
+        assert!(matches!(adapter.remove_policies("p", "p", rules), Err(Error::Adapter(_))));
+        assert_eq!(sorted_policy(&adapter), policy);
+    }
+
+    #[test]
+    fn test_update_rollback() {
+        let adapter = FailingAdapter::default();
+        adapter.add_policy("p", "p", to_strings(&["alice", "data1", "read"])).unwrap();
+        adapter.add_policy("p", "p", to_strings(&["bob", "data2", "write"])).unwrap();
+        let policy = sorted_policy(&adapter);
+
+        let old_rule = to_strings(&["alice", "data1", "read"]);
+        assert!(matches!(
+            adapter.update_policy("p", "p", old_rule, to_strings(&["error", "data1", "read"])),
+            Err(Error::Adapter(_))
+        ));
+        assert_eq!(sorted_policy(&adapter), policy);
+
+        let old_rules = vec![to_strings(&["alice", "data1", "read"]), to_strings(&["bob", "data2", "write"])];
+        let new_rules = vec![to_strings(&["alice", "data1", "write"]), to_strings(&["error", "data2", "write"])];
+        assert!(matches!(adapter.update_policies("p", "p", old_rules, new_rules), Err(Error::Adapter(_))));
+        assert_eq!(sorted_policy(&adapter), policy);
+    }
+}
@@ -1,10 +1,9 @@
-use std::fs::File;
-use std::io::Read;
+use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::error::Error;
+use crate::error::{AdapterError, Error};
 use crate::model::Model;
-use crate::persist::{Adapter, Filter, FilteredAdapter};
+use crate::persist::{load_policy_text, policy_text, Adapter, Filter, FilteredAdapter};
 
 #[derive(Debug)]
 pub struct FileAdapter {
@@ -17,66 +16,55 @@ impl FileAdapter {
     pub fn new<P: AsRef<Path>>(path: P, filtered: bool) -> Self {
         FileAdapter {
             path: path.as_ref().to_path_buf(),
-            filtered: filtered,
+            filtered,
         }
     }
+
+    fn read_contents(&self) -> Result<String, Error> {
+        fs::read_to_string(&self.path).map_err(|err| {
+            let message = format!("cannot read the policy file {}", self.path.display());
+            AdapterError::with_source(&message, err).into()
+        })
+    }
 }
 
 impl Adapter for FileAdapter {
     /// Load all policy rules from the storage.
     fn load_policy(&self, model: &mut Model) -> Result<(), Error> {
-        let mut file = File::open(&self.path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-
-        for line in contents.lines() {
-            model.load_policy_line(line)?;
-        }
-
-        Ok(())
+        load_policy_text(model, &self.read_contents()?, None)
     }
 
+    /// Write all policy rules to the storage, replacing its content.
     fn save_policy(&self, model: &mut Model) -> Result<(), Error> {
-        unimplemented!()
+        fs::write(&self.path, policy_text(model)).map_err(|err| {
+            let message = format!("cannot write the policy file {}", self.path.display());
+            AdapterError::with_source(&message, err).into()
+        })
     }
 
-    fn add_policy(&self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
-        unimplemented!()
+    // The file is only written as a whole by `save_policy`.
+    fn add_policy(&self, _sec: &str, _ptype: &str, _rule: Vec<String>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
     }
 
-    fn remove_policy(&self, sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
-        unimplemented!()
+    fn remove_policy(&self, _sec: &str, _ptype: &str, _rule: Vec<String>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
     }
 
     fn remove_filtered_policy(
         &self,
-        sec: &str,
-        ptype: &str,
-        field_index: i32,
-        field_values: Vec<String>,
+        _sec: &str,
+        _ptype: &str,
+        _field_index: i32,
+        _field_values: Vec<String>,
     ) -> Result<(), Error> {
-        unimplemented!()
+        Err(Error::NotImplemented)
     }
 }
 
 impl FilteredAdapter for FileAdapter {
     fn load_filtered_policy(&self, model: &mut Model, filter: Option<&Filter>) -> Result<(), Error> {
-        if filter.is_none() {
-            return self.load_policy(model);
-        }
-
-        let mut file = File::open(&self.path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-
-        for line in contents.lines() {
-            if filter_line(line, filter) {
-                continue;
-            }
-            model.load_policy_line(line)?;
-        }
-
-        Ok(())
+        load_policy_text(model, &self.read_contents()?, filter)
     }
 
     fn is_filtered(&self) -> bool {
@@ -84,49 +72,66 @@ impl FilteredAdapter for FileAdapter {
     }
 }
 
-fn filter_line(line: &str, filter: Option<&Filter>) -> bool {
-    if filter.is_none() {
-        return false;
-    }
-    let filter = filter.unwrap();
-
-    let p: Vec<String> = line.split(',').map(|t| t.trim().to_string()).collect();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if p.is_empty() {
-        return true;
+    #[test]
+    fn test_load_policy() {
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/basic_policy.csv", false);
+        adapter.load_policy(&mut model).expect("failed to load policy");
     }
 
-    match p[0].as_str() {
-        "p" => filter_words(p, &filter.p),
-        "g" => filter_words(p, &filter.g),
-        _ => panic!("unexpected line filter"),
+    #[test]
+    fn test_load_policy_with_invalid_rule() {
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/error/short_policy.csv", false);
+        let err = adapter.load_policy(&mut model).unwrap_err();
+        assert!(matches!(err, Error::Policy(ref err) if err.line == Some(2)));
+        assert_eq!(
+            err.to_string(),
+            "Invalid policy rule: p, bob, data2 (line 2): expected 3 fields (sub, obj, act), found 2"
+        );
     }
-}
 
-fn filter_words(line: Vec<String>, filter: &Vec<String>) -> bool {
-    if line.len() < filter.len() + 1 {
-        return true;
-    }
+    #[test]
+    fn test_load_missing_policy() {
+        use std::error::Error as StdError;
 
-    let mut skip_line = false;
-    for (i, v) in filter.iter().enumerate() {
-        if v.len() > 0 && v.trim() != line[i + 1].trim() {
-            skip_line = true;
-            break;
-        }
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/missing_policy.csv", false);
+        let err = adapter.load_policy(&mut model).unwrap_err();
+        assert!(matches!(err, Error::Adapter(_)));
+        let source = err.source().unwrap().source().unwrap().downcast_ref::<std::io::Error>().unwrap();
+        assert_eq!(source.kind(), std::io::ErrorKind::NotFound);
     }
 
-    skip_line
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
     #[test]
-    fn test_load_policy() {
-        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
-        let adapter = FileAdapter::new("examples/basic_policy.csv", false);
+    fn test_save_policy() {
+        let mut model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        let adapter = FileAdapter::new("examples/rbac_policy.csv", false);
         adapter.load_policy(&mut model).expect("failed to load policy");
+        model
+            .add_policy("p", "p", &["#admin", "/api/items?a=1,2", "say \"hi\""])
+            .unwrap();
+
+        let path = std::env::temp_dir().join(format!("casbin_test_save_policy_{}.csv", std::process::id()));
+        let adapter = FileAdapter::new(&path, false);
+        adapter.save_policy(&mut model).expect("failed to save policy");
+
+        let mut saved = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        adapter.load_policy(&mut saved).expect("failed to load saved policy");
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(saved.get_policy("p", "p"), model.get_policy("p", "p"));
+        assert_eq!(saved.get_policy("g", "g"), model.get_policy("g", "g"));
+        assert_eq!(saved.has_policy("p", "p", &["#admin", "/api/items?a=1,2", "say \"hi\""]), true);
+
+        // a value cannot be written on a single line
+        assert!(matches!(
+            model.add_policy("p", "p", &["alice", "data1\nread", "read"]),
+            Err(Error::Policy(_))
+        ));
     }
 }
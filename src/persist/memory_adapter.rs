@@ -0,0 +1,230 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::error::{AdapterError, Error};
+use crate::model::Model;
+use crate::persist::{Adapter, Filter, FilteredAdapter};
+
+/// An adapter that keeps the policy in memory, e.g. for tests or a policy built by the program.
+///
+/// The rules are stored per policy type in the order they were added, the section of a policy type is
+/// its first letter (`p` for `p2`, `g` for `g2`).
+#[derive(Debug, Default)]
+pub struct MemoryAdapter {
+    policy: RwLock<HashMap<String, Vec<Vec<String>>>>,
+    // whether the last load was filtered, the whole policy cannot be saved then
+    filtered: AtomicBool,
+}
+
+impl MemoryAdapter {
+    /// Create a MemoryAdapter instance with an empty policy.
+    pub fn new() -> Self {
+        MemoryAdapter::default()
+    }
+
+    /// Get the stored rules of the policy type `ptype`.
+    pub fn get_policy(&self, ptype: &str) -> Vec<Vec<String>> {
+        let policy = self.policy.read().unwrap();
+        policy.get(ptype).cloned().unwrap_or_default()
+    }
+
+    fn load(&self, model: &mut Model, filter: Option<&Filter>) -> Result<(), Error> {
+        let policy = self.policy.read().unwrap();
+
+        // load the policy types in a stable order so that errors do not depend on the hash map
+        let mut ptypes: Vec<&String> = policy.keys().collect();
+        ptypes.sort();
+
+        for ptype in ptypes {
+            let sec = ptype.get(..1).unwrap_or_default();
+            for rule in &policy[ptype] {
+                if filter.is_some_and(|filter| !filter.keeps(sec, rule)) {
+                    continue;
+                }
+                let rule: Vec<&str> = rule.iter().map(|s| s.as_str()).collect();
+                model.add_policy(sec, ptype, &rule)?;
+            }
+        }
+
+        self.filtered.store(filter.is_some(), Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+impl Adapter for MemoryAdapter {
+    /// Load all policy rules from the storage.
+    fn load_policy(&self, model: &mut Model) -> Result<(), Error> {
+        self.load(model, None)
+    }
+
+    /// Replace the stored policy by the policy of `model`.
+    fn save_policy(&self, model: &mut Model) -> Result<(), Error> {
+        if self.is_filtered() {
+            return Err(AdapterError::new("cannot save a filtered policy").into());
+        }
+
+        let mut policy = self.policy.write().unwrap();
+        policy.clear();
+        for sec in &["p", "g"] {
+            for ptype in model.get_ptypes(sec) {
+                let rules = model.get_policy(sec, &ptype).unwrap_or_default();
+                if !rules.is_empty() {
+                    policy.insert(ptype, rules);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn add_policy(&self, _sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
+        let mut policy = self.policy.write().unwrap();
+        policy.entry(ptype.to_string()).or_default().push(rule);
+        Ok(())
+    }
+
+    fn remove_policy(&self, _sec: &str, ptype: &str, rule: Vec<String>) -> Result<(), Error> {
+        let mut policy = self.policy.write().unwrap();
+        if let Some(rules) = policy.get_mut(ptype) {
+            if let Some(position) = rules.iter().position(|r| *r == rule) {
+                rules.remove(position);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_filtered_policy(
+        &self,
+        _sec: &str,
+        ptype: &str,
+        field_index: i32,
+        field_values: Vec<String>,
+    ) -> Result<(), Error> {
+        let mut policy = self.policy.write().unwrap();
+        if let Some(rules) = policy.get_mut(ptype) {
+            let field_index = field_index as usize;
+            rules.retain(|rule| {
+                !field_values
+                    .iter()
+                    .enumerate()
+                    .all(|(i, value)| match rule.get(field_index + i) {
+                        Some(field) => value.is_empty() || field == value,
+                        None => false,
+                    })
+            });
+        }
+        Ok(())
+    }
+
+    /// Replace the rule `old_rule` by `new_rule`, keeping its position.
+    fn update_policy(
+        &self,
+        _sec: &str,
+        ptype: &str,
+        old_rule: Vec<String>,
+        new_rule: Vec<String>,
+    ) -> Result<(), Error> {
+        let mut policy = self.policy.write().unwrap();
+        if let Some(rule) = policy
+            .get_mut(ptype)
+            .and_then(|rules| rules.iter_mut().find(|r| **r == old_rule))
+        {
+            *rule = new_rule;
+        }
+        Ok(())
+    }
+}
+
+impl FilteredAdapter for MemoryAdapter {
+    fn load_filtered_policy(&self, model: &mut Model, filter: Option<&Filter>) -> Result<(), Error> {
+        self.load(model, filter)
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.filtered.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::to_strings;
+
+    #[test]
+    fn test_memory_adapter() {
+        let adapter = MemoryAdapter::new();
+        adapter.add_policy("p", "p", to_strings(&["alice", "data1", "read"])).unwrap();
+        adapter.add_policy("p", "p", to_strings(&["bob", "data2", "write"])).unwrap();
+        adapter
+            .add_policy("p", "p", to_strings(&["data2_admin", "data2", "read"]))
+            .unwrap();
+        adapter.add_policy("g", "g", to_strings(&["alice", "data2_admin"])).unwrap();
+
+        let mut model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        adapter.load_policy(&mut model).expect("failed to load policy");
+        assert_eq!(model.get_policy("p", "p").unwrap(), adapter.get_policy("p"));
+        assert_eq!(
+            model.get_policy("g", "g").unwrap(),
+            vec![to_strings(&["alice", "data2_admin"])]
+        );
+
+        adapter
+            .remove_policy("p", "p", to_strings(&["bob", "data2", "write"]))
+            .unwrap();
+        adapter
+            .update_policy(
+                "p",
+                "p",
+                to_strings(&["alice", "data1", "read"]),
+                to_strings(&["alice", "data1", "write"]),
+            )
+            .unwrap();
+        adapter
+            .remove_filtered_policy("p", "p", 1, vec!["data2".to_string()])
+            .unwrap();
+        assert_eq!(adapter.get_policy("p"), vec![to_strings(&["alice", "data1", "write"])]);
+
+        model.add_policy("p", "p", &["eve", "data3", "read"]).unwrap();
+        adapter.save_policy(&mut model).expect("failed to save policy");
+        assert_eq!(adapter.get_policy("p"), model.get_policy("p", "p").unwrap());
+        assert_eq!(adapter.get_policy("g"), model.get_policy("g", "g").unwrap());
+    }
+
+    #[test]
+    fn test_load_filtered_policy() {
+        let adapter = MemoryAdapter::new();
+        adapter
+            .add_policy("p", "p", to_strings(&["admin", "domain1", "data1", "read"]))
+            .unwrap();
+        adapter
+            .add_policy("p", "p", to_strings(&["admin", "domain2", "data2", "read"]))
+            .unwrap();
+        adapter
+            .add_policy("g", "g", to_strings(&["alice", "admin", "domain1"]))
+            .unwrap();
+        adapter
+            .add_policy("g", "g", to_strings(&["bob", "admin", "domain2"]))
+            .unwrap();
+
+        let filter = Filter {
+            p: vec!["".to_string(), "domain1".to_string()],
+            g: vec!["".to_string(), "".to_string(), "domain1".to_string()],
+        };
+        let mut model = Model::from_file("examples/rbac_with_domains_model.conf").expect("failed to load model");
+        adapter
+            .load_filtered_policy(&mut model, Some(&filter))
+            .expect("failed to load policy");
+        assert_eq!(adapter.is_filtered(), true);
+        assert_eq!(
+            model.get_policy("p", "p").unwrap(),
+            vec![to_strings(&["admin", "domain1", "data1", "read"])]
+        );
+        assert_eq!(
+            model.get_policy("g", "g").unwrap(),
+            vec![to_strings(&["alice", "admin", "domain1"])]
+        );
+
+        assert!(matches!(adapter.save_policy(&mut model), Err(Error::Adapter(_))));
+        assert_eq!(adapter.get_policy("p").len(), 2);
+    }
+}
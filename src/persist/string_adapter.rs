@@ -0,0 +1,215 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use crate::error::{AdapterError, Error};
+use crate::model::Model;
+use crate::persist::{load_policy_text, policy_text, Adapter, Filter, FilteredAdapter};
+
+type WriterFactory = dyn Fn() -> io::Result<Box<dyn Write>> + Send + Sync;
+
+/// An adapter that loads the policy from a CSV text, in the format of `FileAdapter`.
+///
+/// The text can be given as a string, e.g. embedded with `include_str!`, as bytes or read from any
+/// reader. `save_policy` replaces the text and, if the adapter has a writer, writes it there too.
+pub struct StringAdapter {
+    text: RwLock<String>,
+    writer: Option<Box<WriterFactory>>,
+    // whether the last load was filtered, the whole policy cannot be saved then
+    filtered: AtomicBool,
+}
+
+impl StringAdapter {
+    /// Create a StringAdapter instance from the policy `text`.
+    pub fn new<S: Into<String>>(text: S) -> Self {
+        StringAdapter {
+            text: RwLock::new(text.into()),
+            writer: None,
+            filtered: AtomicBool::new(false),
+        }
+    }
+
+    /// Create a StringAdapter instance from a policy text encoded in UTF-8.
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Error> {
+        match String::from_utf8(bytes) {
+            Ok(text) => Ok(StringAdapter::new(text)),
+            Err(err) => Err(AdapterError::with_source("the policy is not valid UTF-8", err).into()),
+        }
+    }
+
+    /// Create a StringAdapter instance from the policy text read from `reader` until its end.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut text = String::new();
+        match reader.read_to_string(&mut text) {
+            Ok(_) => Ok(StringAdapter::new(text)),
+            Err(err) => Err(AdapterError::with_source("cannot read the policy", err).into()),
+        }
+    }
+
+    /// Write the policy as well when it is saved, to a new writer created by `writer` for each save.
+    ///
+    /// Every save writes the whole policy, so the writer should start empty, e.g.
+    /// `|| File::create("policy.csv")`.
+    pub fn with_writer<W, F>(mut self, writer: F) -> Self
+    where
+        W: Write + 'static,
+        F: Fn() -> io::Result<W> + Send + Sync + 'static,
+    {
+        self.writer = Some(Box::new(move || writer().map(|w| Box::new(w) as Box<dyn Write>)));
+        self
+    }
+
+    /// Get the policy text, as last saved.
+    pub fn text(&self) -> String {
+        self.text.read().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for StringAdapter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StringAdapter")
+            .field("text", &self.text)
+            .field("writer", &self.writer.is_some())
+            .field("filtered", &self.filtered)
+            .finish()
+    }
+}
+
+impl Adapter for StringAdapter {
+    /// Load all policy rules from the text.
+    fn load_policy(&self, model: &mut Model) -> Result<(), Error> {
+        self.load_filtered_policy(model, None)
+    }
+
+    /// Replace the text by the policy of `model`, and write it to the writer if any.
+    fn save_policy(&self, model: &mut Model) -> Result<(), Error> {
+        if self.is_filtered() {
+            return Err(AdapterError::new("cannot save a filtered policy").into());
+        }
+
+        let text = policy_text(model);
+        if let Some(ref writer) = self.writer {
+            writer()
+                .and_then(|mut writer| writer.write_all(text.as_bytes()).and_then(|_| writer.flush()))
+                .map_err(|err| AdapterError::with_source("cannot write the policy", err))?;
+        }
+        *self.text.write().unwrap() = text;
+        Ok(())
+    }
+
+    // The text is only written as a whole by `save_policy`.
+    fn add_policy(&self, _sec: &str, _ptype: &str, _rule: Vec<String>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn remove_policy(&self, _sec: &str, _ptype: &str, _rule: Vec<String>) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+
+    fn remove_filtered_policy(
+        &self,
+        _sec: &str,
+        _ptype: &str,
+        _field_index: i32,
+        _field_values: Vec<String>,
+    ) -> Result<(), Error> {
+        Err(Error::NotImplemented)
+    }
+}
+
+impl FilteredAdapter for StringAdapter {
+    fn load_filtered_policy(&self, model: &mut Model, filter: Option<&Filter>) -> Result<(), Error> {
+        load_policy_text(model, &self.text.read().unwrap(), filter)?;
+        self.filtered.store(filter.is_some(), Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.filtered.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    // A writer whose content can be checked after it is moved into the adapter.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_load_policy() {
+        let text = include_str!("../../examples/rbac_policy.csv");
+        let adapters = vec![
+            StringAdapter::new(text),
+            StringAdapter::from_bytes(text.as_bytes().to_vec()).unwrap(),
+            StringAdapter::from_reader(std::fs::File::open("examples/rbac_policy.csv").unwrap()).unwrap(),
+        ];
+
+        let mut expected = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+        load_policy_text(&mut expected, text, None).unwrap();
+        for adapter in adapters {
+            let mut model = Model::from_file("examples/rbac_model.conf").expect("failed to load model");
+            adapter.load_policy(&mut model).expect("failed to load policy");
+            assert_eq!(model.get_policy("p", "p"), expected.get_policy("p", "p"));
+            assert_eq!(model.get_policy("g", "g"), expected.get_policy("g", "g"));
+        }
+
+        assert!(matches!(StringAdapter::from_bytes(vec![0xff, 0xfe]), Err(Error::Adapter(_))));
+    }
+
+    #[test]
+    fn test_load_policy_with_invalid_rule() {
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        let adapter = StringAdapter::new("p, alice, data1, read\n\np, bob, data2\n");
+        assert!(matches!(adapter.load_policy(&mut model), Err(Error::Policy(err)) if err.line == Some(3)));
+    }
+
+    #[test]
+    fn test_save_policy() {
+        let buffer = SharedBuffer::default();
+        let writer = buffer.clone();
+        let adapter = StringAdapter::new("p, alice, data1, read\n").with_writer(move || {
+            writer.0.lock().unwrap().clear();
+            Ok(writer.clone())
+        });
+
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        adapter.load_policy(&mut model).expect("failed to load policy");
+        model.add_policy("p", "p", &["bob", "data2", "say \"hi\""]).unwrap();
+        adapter.save_policy(&mut model).expect("failed to save policy");
+
+        let expected = "p, alice, data1, read\np, bob, data2, \"say \"\"hi\"\"\"\n";
+        assert_eq!(adapter.text(), expected);
+        assert_eq!(String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap(), expected);
+
+        // a later save replaces what the previous one wrote
+        model.remove_policy("p", "p", &["alice", "data1", "read"]);
+        adapter.save_policy(&mut model).expect("failed to save policy");
+        let expected = "p, bob, data2, \"say \"\"hi\"\"\"\n";
+        assert_eq!(String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap(), expected);
+
+        let filter = Filter {
+            p: vec!["bob".to_string()],
+            g: Vec::new(),
+        };
+        let mut model = Model::from_file("examples/basic_model.conf").expect("failed to load model");
+        adapter
+            .load_filtered_policy(&mut model, Some(&filter))
+            .expect("failed to load policy");
+        assert_eq!(model.get_policy("p", "p").unwrap().len(), 1);
+        assert!(matches!(adapter.save_policy(&mut model), Err(Error::Adapter(_))));
+    }
+}
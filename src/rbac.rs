@@ -1,11 +1,13 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt;
-use std::sync::{Arc, Mutex};
 
 use crate::error::Error;
 
 mod default_role_manager;
+mod role_graph;
 
 pub use crate::rbac::default_role_manager::DefaultRoleManager;
+pub use crate::rbac::role_graph::{RoleGraph, RoleLink, RoleNode};
 
 pub type Function = Fn(&str, &str) -> bool + Sync + Send;
 
@@ -21,101 +23,104 @@ pub trait RoleManager {
     fn clear(&mut self) -> Result<(), Error>;
     fn add_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> Result<(), Error>;
     fn delete_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> Result<(), Error>;
-    fn has_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> bool;
+    fn has_link(&self, name1: &str, name2: &str, domain: Option<&str>) -> bool;
     fn get_roles(&self, name: &str, domain: Option<&str>) -> Vec<String>;
     fn get_users(&self, name: &str, domain: Option<&str>) -> Vec<String>;
     fn print_roles(&self) -> Result<(), Error>;
     fn add_matching_function(&mut self, name: &str, matching_func: MatchingFunction);
-}
-
-#[derive(Clone, Debug)]
-pub struct Role {
-    pub name: String,
-    roles: Vec<Arc<Mutex<Role>>>,
-}
 
-impl Role {
-    pub fn new(name: &str) -> Self {
-        Role {
-            name: name.to_string(),
-            roles: Vec::new(),
-        }
+    /// Export the roles and inheritance links, role managers that cannot list them return an empty
+    /// graph.
+    fn get_role_graph(&self) -> RoleGraph {
+        RoleGraph::new()
     }
 
-    fn roles_contains(&self, name: &str) -> bool {
-        for role in &self.roles {
-            if role.lock().unwrap().name == name {
-                return true;
+    /// Get the list of roles that `name` inherits directly or indirectly, closest roles first.
+    fn get_implicit_roles(&self, name: &str, domain: Option<&str>) -> Vec<String> {
+        let mut res = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(name.to_string());
+        queue.push_back(name.to_string());
+
+        while let Some(current) = queue.pop_front() {
+            for role in self.get_roles(&current, domain) {
+                if seen.insert(role.clone()) {
+                    res.push(role.clone());
+                    queue.push_back(role);
+                }
             }
         }
 
-        false
+        res
     }
 
-    pub fn add_role(&mut self, role: Arc<Mutex<Role>>) {
-        let name = role.lock().unwrap().to_string();
-        if !self.roles_contains(&name) {
-            self.roles.push(role);
-        }
-    }
-
-    pub fn delete_role(&mut self, role: Arc<Mutex<Role>>) {
-        let name = role.lock().unwrap().name.to_string();
-        let index = self.roles.iter().position(|x| x.lock().unwrap().name == name);
-        if let Some(index) = index {
-            self.roles.remove(index);
-        }
+    /// Return true if `get_implicit_roles` lists every role `name2` that `has_link` is true for, besides
+    /// `name1` itself.
+    ///
+    /// The enforcer then only evaluates the rules of these roles. Role managers that match names by
+    /// patterns cannot list them and keep the default.
+    fn can_enumerate_roles(&self) -> bool {
+        false
     }
 
-    pub fn has_role(&self, name: &str, hierarchy_level: i32) -> bool {
-        if self.name == name {
-            return true;
-        }
-
-        if hierarchy_level <= 0 {
-            return false;
-        }
-
-        for role in &self.roles {
-            if role.lock().unwrap().has_role(name, hierarchy_level - 1) {
-                return true;
+    /// Get the list of users that inherit `name` directly or indirectly, closest users first.
+    fn get_implicit_users(&self, name: &str, domain: Option<&str>) -> Vec<String> {
+        let mut res = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::new();
+        seen.insert(name.to_string());
+        queue.push_back(name.to_string());
+
+        while let Some(current) = queue.pop_front() {
+            for user in self.get_users(&current, domain) {
+                if seen.insert(user.clone()) {
+                    res.push(user.clone());
+                    queue.push_back(user);
+                }
             }
         }
 
-        false
+        res
     }
 
-    pub fn has_direct_role(&self, name: &str) -> bool {
-        for role in &self.roles {
-            if name == role.lock().unwrap().name {
-                return true;
-            }
-        }
+    /// Add a link such that `name1` inherits the role `name2`.
+    ///
+    /// `fields` holds the values following the two names in the role definition. By default they
+    /// are joined into a single domain, see `join_fields`, role managers may override this to
+    /// interpret them.
+    fn add_link_with_fields(&mut self, name1: &str, name2: &str, fields: &[&str]) -> Result<(), Error> {
+        let domain = join_fields(fields);
+        self.add_link(name1, name2, domain.as_deref())
+    }
 
-        false
+    /// Delete the inheritance link between `name1` and `name2`, see `add_link_with_fields`.
+    fn delete_link_with_fields(&mut self, name1: &str, name2: &str, fields: &[&str]) -> Result<(), Error> {
+        let domain = join_fields(fields);
+        self.delete_link(name1, name2, domain.as_deref())
     }
 
-    pub fn get_roles(&self) -> Vec<String> {
-        let mut roles = Vec::new();
-        for role in &self.roles {
-            roles.push(role.lock().unwrap().name.clone());
-        }
-        roles
+    /// Get the list of roles that `name` inherits directly or indirectly, see `add_link_with_fields`.
+    fn get_implicit_roles_with_fields(&self, name: &str, fields: &[&str]) -> Vec<String> {
+        let domain = join_fields(fields);
+        self.get_implicit_roles(name, domain.as_deref())
     }
-}
 
-impl PartialEq for Role {
-    fn eq(&self, other: &Role) -> bool {
-        self.name == other.name
+    /// Return true if `name1` inherits the role `name2`, see `add_link_with_fields`.
+    fn has_link_with_fields(&self, name1: &str, name2: &str, fields: &[&str]) -> bool {
+        let domain = join_fields(fields);
+        self.has_link(name1, name2, domain.as_deref())
     }
 }
 
-impl fmt::Display for Role {
-    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_str(self.name.as_ref())?;
-        fmt.write_str(" < ")?;
-        let roles = self.get_roles();
-        fmt.write_str(&roles.join(","))?;
-        Ok(())
+/// Join the extra fields of a grouping rule into a single domain.
+///
+/// The fields are separated by the ASCII unit separator, so that e.g. `["a", "b"]` and `["a::b"]` are
+/// different domains. Field values containing that control character are not supported.
+fn join_fields(fields: &[&str]) -> Option<String> {
+    if fields.is_empty() {
+        None
+    } else {
+        Some(fields.join("\u{1f}"))
     }
 }
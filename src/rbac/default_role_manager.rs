@@ -1,182 +1,360 @@
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::fmt;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
 
-use crate::error::Error;
-use crate::rbac::{Role, RoleManager, MatchingFunction};
+use crate::error::{Error, RoleManagerError};
+use crate::rbac::{MatchingFunction, RoleGraph, RoleLink, RoleManager, RoleNode};
+use crate::util::to_strings;
+
+/// A node of the role graph, links are stored as indexes into `DefaultRoleManager::roles`.
+#[derive(Debug)]
+struct Role {
+    name: String,
+    domain: Vec<String>,
+    roles: Vec<usize>,
+    users: Vec<usize>,
+}
+
+impl Role {
+    fn new(name: &str, domain: &[String]) -> Self {
+        Role {
+            name: name.to_string(),
+            domain: domain.to_vec(),
+            roles: Vec::new(),
+            users: Vec::new(),
+        }
+    }
+}
+
+/// The roles inherited by a role, in breadth-first order, with the number of links to each of them.
+#[derive(Debug)]
+struct Closure {
+    roles: Vec<usize>,
+    levels: HashMap<usize, i32>,
+}
 
 #[derive(Debug)]
 pub struct DefaultRoleManager {
-    roles: HashMap<String, Arc<Mutex<Role>>>,
+    // the roles of each domain by name, roles without a domain are stored under an empty one
+    all_roles: HashMap<Vec<String>, HashMap<String, usize>>,
+    roles: Vec<Role>,
+    closures: RwLock<HashMap<usize, Arc<Closure>>>,
     max_hierarchy_level: i32,
     has_pattern: bool,
-    matching_function: Option<MatchingFunction>
+    matching_function: Option<MatchingFunction>,
 }
 
 impl RoleManager for DefaultRoleManager {
     /// Clear all stored data and reset the role manager to the initial state.
     fn clear(&mut self) -> Result<(), Error> {
+        self.all_roles.clear();
         self.roles.clear();
+        self.closures.get_mut().unwrap().clear();
         Ok(())
     }
 
     /// Add a link such that `name1` inherits the role `name2`.
     ///
-    /// `domain` is a prefix to the roles.
+    /// Roles of different domains are unrelated. Returns an error if the link would create a cycle.
     fn add_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> Result<(), Error> {
-        let (name1, name2) = DefaultRoleManager::get_names_with_domain(name1, name2, domain);
+        self.add_link_in(name1, name2, &domain_fields(domain))
+    }
 
-        let role1 = self.create_role(&name1);
-        let role2 = self.create_role(&name2);
+    /// Delete the inheritance link between `name1` and  `name2` in `domain`.
+    fn delete_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> Result<(), Error> {
+        self.delete_link_in(name1, name2, &domain_fields(domain))
+    }
 
-        role1.lock().unwrap().add_role(role2);
+    /// Return true if `name1` inherits the role `name2` in `domain`.
+    fn has_link(&self, name1: &str, name2: &str, domain: Option<&str>) -> bool {
+        self.has_link_in(name1, name2, &domain_fields(domain))
+    }
 
-        Ok(())
+    /// Get the list of roles that `name` inherits in `domain`.
+    fn get_roles(&self, name: &str, domain: Option<&str>) -> Vec<String> {
+        match self.get_role(name, &domain_fields(domain)) {
+            Some(role) => self.names(&self.roles[role].roles),
+            None => Vec::new(),
+        }
     }
 
-    /// Delete the inheritance link between `name1` and  `name2`.
-    ///
-    /// `domain` is a prefix to the roles.
-    fn delete_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> Result<(), Error> {
-        let (name1, name2) = DefaultRoleManager::get_names_with_domain(name1, name2, domain);
+    /// Get the list of users that inherit `name` in `domain`.
+    fn get_users(&self, name: &str, domain: Option<&str>) -> Vec<String> {
+        match self.get_role(name, &domain_fields(domain)) {
+            Some(role) => self.names(&self.roles[role].users),
+            None => Vec::new(),
+        }
+    }
 
-        let role1 = self.get_role(&name1).ok_or(Error::MissingRole(name1.clone()))?;
-        let role2 = self.get_role(&name2).ok_or(Error::MissingRole(name2.clone()))?;
+    /// Get the list of roles that `name` inherits directly or indirectly in `domain`, closest roles
+    /// first.
+    fn get_implicit_roles(&self, name: &str, domain: Option<&str>) -> Vec<String> {
+        self.get_implicit_roles_in(name, &domain_fields(domain))
+    }
 
-        role1.lock().unwrap().delete_role(role2);
-        Ok(())
+    /// Add a link such that `name1` inherits the role `name2`, in the domain made of `fields`.
+    fn add_link_with_fields(&mut self, name1: &str, name2: &str, fields: &[&str]) -> Result<(), Error> {
+        self.add_link_in(name1, name2, &to_strings(fields))
     }
 
-    /// Return true if `name1` inherits the role `name2`.
-    fn has_link(&mut self, name1: &str, name2: &str, domain: Option<&str>) -> bool {
-        let (name1, name2) = DefaultRoleManager::get_names_with_domain(name1, name2, domain);
+    fn delete_link_with_fields(&mut self, name1: &str, name2: &str, fields: &[&str]) -> Result<(), Error> {
+        self.delete_link_in(name1, name2, &to_strings(fields))
+    }
 
-        if name1 == name2 {
-            return true;
-        }
+    fn get_implicit_roles_with_fields(&self, name: &str, fields: &[&str]) -> Vec<String> {
+        self.get_implicit_roles_in(name, &to_strings(fields))
+    }
 
-        if !self.has_role(&name1) || !self.has_role(&name2){
-            return false;
-        }
+    fn has_link_with_fields(&self, name1: &str, name2: &str, fields: &[&str]) -> bool {
+        self.has_link_in(name1, name2, &to_strings(fields))
+    }
 
-        let role1 = self.create_role(&name1);
-        let result = role1.lock().unwrap().has_role(&name2, self.max_hierarchy_level);
-        result
+    fn can_enumerate_roles(&self) -> bool {
+        !self.has_pattern
     }
 
-    /// Get the list of roles that `name` inherits.
-    ///
-    /// `domain` is a prefix to the role.
-    fn get_roles(&self, name: &str, domain: Option<&str>) -> Vec<String> {
-        let name = DefaultRoleManager::get_name_with_domain(name, domain);
-        if let Some(role) = self.get_role(&name) {
-            role.lock().unwrap().get_roles()
-        } else {
-            Vec::new()
-        }
+    /// Print every role with the roles it inherits.
+    fn print_roles(&self) -> Result<(), Error> {
+        print!("{}", self.get_role_graph());
+        Ok(())
     }
 
-    /// Get the list of users that inherit `name`.
-    ///
-    /// `domain` is a prefix to the role.
-    fn get_users(&self, name: &str, domain: Option<&str>) -> Vec<String> {
-        let name = DefaultRoleManager::get_name_with_domain(name, domain);
+    /// Export the roles and links with the domain of each role.
+    fn get_role_graph(&self) -> RoleGraph {
+        let mut graph = RoleGraph::new();
 
-        if !self.has_role(&name) {
-            return Vec::new();
+        for role in &self.roles {
+            graph.nodes.push(RoleNode {
+                name: role.name.clone(),
+                domain: graph_domain(&role.domain),
+            });
         }
 
-        let mut names = vec![];
-
-        for (role_name, role) in &self.roles {
-            if role.lock().unwrap().has_direct_role(&name) {
-                names.push(role_name.clone())
+        for role in &self.roles {
+            for &parent in &role.roles {
+                graph.links.push(RoleLink {
+                    user: role.name.clone(),
+                    role: self.roles[parent].name.clone(),
+                    domain: graph_domain(&role.domain),
+                });
             }
         }
 
-        names
-    }
-
-    fn print_roles(&self) -> Result<(), Error> {
-        unimplemented!()
+        graph
     }
 
-    fn add_matching_function(&mut self, name: &str, matching_func: MatchingFunction){
+    fn add_matching_function(&mut self, _name: &str, matching_func: MatchingFunction) {
         self.has_pattern = true;
         self.matching_function = Some(matching_func);
     }
 }
 
+impl Default for DefaultRoleManager {
+    fn default() -> Self {
+        DefaultRoleManager::new(10)
+    }
+}
+
 impl DefaultRoleManager {
     pub fn new(max_hierarchy_level: i32) -> Self {
         DefaultRoleManager {
-            roles: HashMap::new(),
+            all_roles: HashMap::new(),
+            roles: Vec::new(),
+            closures: RwLock::new(HashMap::new()),
             max_hierarchy_level,
             has_pattern: false,
-            matching_function: None
+            matching_function: None,
         }
     }
 
-    fn has_role(&self, name: &str) -> bool {
-        if self.has_pattern {
-            if let Some(func) = &self.matching_function {
-                let f = &func.0;
-                for role in &self.roles {
-                    let key = role.0;
-                    if f(name, key) {
-                        return true;
-                    }
-                }
+    fn add_link_in(&mut self, name1: &str, name2: &str, domain: &[String]) -> Result<(), Error> {
+        if let (Some(role1), Some(role2)) = (self.find_role(name1, domain), self.find_role(name2, domain)) {
+            if role1 == role2 || self.inherits(role2, role1) {
+                return Err(RoleManagerError::RoleCycle(full_name(name1, domain), full_name(name2, domain)).into());
             }
+        } else if name1 == name2 {
+            return Err(RoleManagerError::RoleCycle(full_name(name1, domain), full_name(name2, domain)).into());
         }
-        self.roles.contains_key(name)
+
+        let role1 = self.create_role(name1, domain);
+        let role2 = self.create_role(name2, domain);
+
+        if !self.roles[role1].roles.contains(&role2) {
+            self.roles[role1].roles.push(role2);
+            self.roles[role2].users.push(role1);
+            self.invalidate_closures(role1);
+        }
+
+        Ok(())
+    }
+
+    fn delete_link_in(&mut self, name1: &str, name2: &str, domain: &[String]) -> Result<(), Error> {
+        let role1 = self
+            .get_role(name1, domain)
+            .ok_or_else(|| RoleManagerError::MissingRole(full_name(name1, domain)))?;
+        let role2 = self
+            .get_role(name2, domain)
+            .ok_or_else(|| RoleManagerError::MissingRole(full_name(name2, domain)))?;
+
+        self.roles[role1].roles.retain(|&r| r != role2);
+        self.roles[role2].users.retain(|&u| u != role1);
+        self.invalidate_closures(role1);
+        Ok(())
     }
 
-    fn create_role(&mut self, name: &str) -> Arc<Mutex<Role>> {
-        let mut name = name;
+    fn has_link_in(&self, name1: &str, name2: &str, domain: &[String]) -> bool {
+        if name1 == name2 {
+            return true;
+        }
+
+        if self.find_role(name2, domain).is_none() {
+            return false;
+        }
+
+        match (self.find_role(name1, domain), self.get_role(name2, domain)) {
+            (Some(role1), Some(role2)) => match self.closure(role1).levels.get(&role2) {
+                Some(&level) => level <= self.max_hierarchy_level,
+                None => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn get_implicit_roles_in(&self, name: &str, domain: &[String]) -> Vec<String> {
+        match self.get_role(name, domain) {
+            Some(role) => self.names(&self.closure(role).roles),
+            None => Vec::new(),
+        }
+    }
+
+    /// Get the role stored for exactly `name` in `domain`.
+    fn get_role(&self, name: &str, domain: &[String]) -> Option<usize> {
+        self.all_roles.get(domain).and_then(|roles| roles.get(name)).cloned()
+    }
+
+    /// Find the role stored for `name` in `domain`, which may be a pattern matching `name`.
+    fn find_role(&self, name: &str, domain: &[String]) -> Option<usize> {
+        let roles = self.all_roles.get(domain)?;
         if self.has_pattern {
             if let Some(func) = &self.matching_function {
                 let f = &func.0;
-                for role in &self.roles {
-                    let key = &role.0;
+                for (key, &role) in roles {
                     if f(name, key) {
-                        name = key;
-                        break;
+                        return Some(role);
                     }
                 }
             }
         }
+        roles.get(name).cloned()
+    }
 
-        if self.roles.contains_key(name){
-            Arc::clone(self.roles.get(name).unwrap())
-        } else {
-            let role = Arc::new(Mutex::new(Role::new(name)));
-            &self.roles.insert(name.to_string(), Arc::clone(&role));
-            role
+    fn create_role(&mut self, name: &str, domain: &[String]) -> usize {
+        if let Some(role) = self.find_role(name, domain) {
+            return role;
         }
+
+        let role = self.roles.len();
+        self.roles.push(Role::new(name, domain));
+        self.all_roles
+            .entry(domain.to_vec())
+            .or_default()
+            .insert(name.to_string(), role);
+        role
     }
 
-    fn get_role(&self, name: &str) -> Option<Arc<Mutex<Role>>> {
-        Some(Arc::clone(self.roles.get(name)?))
+    /// Get the transitive closure of `role`, computing and caching it if needed.
+    fn closure(&self, role: usize) -> Arc<Closure> {
+        if let Some(closure) = self.closures.read().unwrap().get(&role) {
+            return Arc::clone(closure);
+        }
+
+        let mut closure = Closure {
+            roles: Vec::new(),
+            levels: HashMap::new(),
+        };
+        let mut queue = VecDeque::new();
+        queue.push_back((role, 0));
+
+        while let Some((current, level)) = queue.pop_front() {
+            for &next in &self.roles[current].roles {
+                if next != role && !closure.levels.contains_key(&next) {
+                    closure.roles.push(next);
+                    closure.levels.insert(next, level + 1);
+                    queue.push_back((next, level + 1));
+                }
+            }
+        }
+
+        let closure = Arc::new(closure);
+        self.closures.write().unwrap().insert(role, Arc::clone(&closure));
+        closure
     }
 
-    fn get_name_with_domain(name: &str, domain: Option<&str>) -> String {
-        match domain {
-            Some(domain) => domain.to_string() + "::" + name,
-            None => name.to_string(),
+    /// Drop the cached closures of `role` and of every role inheriting it.
+    fn invalidate_closures(&mut self, role: usize) {
+        let closures = self.closures.get_mut().unwrap();
+        if closures.is_empty() {
+            return;
+        }
+
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(role);
+        queue.push_back(role);
+
+        while let Some(current) = queue.pop_front() {
+            closures.remove(&current);
+            for &user in &self.roles[current].users {
+                if visited.insert(user) {
+                    queue.push_back(user);
+                }
+            }
         }
     }
 
-    fn get_names_with_domain(name1: &str, name2: &str, domain: Option<&str>) -> (String, String) {
-        match domain {
-            Some(domain) => (domain.to_string() + "::" + name1, domain.to_string() + "::" + name2),
-            None => (name1.to_string(), name2.to_string()),
+    /// Return true if `role` inherits `ancestor` through any number of links.
+    fn inherits(&self, role: usize, ancestor: usize) -> bool {
+        let mut visited = HashSet::new();
+        let mut queue = VecDeque::new();
+        visited.insert(role);
+        queue.push_back(role);
+
+        while let Some(current) = queue.pop_front() {
+            if current == ancestor {
+                return true;
+            }
+            for &next in &self.roles[current].roles {
+                if visited.insert(next) {
+                    queue.push_back(next);
+                }
+            }
         }
+
+        false
     }
 
-    fn add_matching_function(&mut self, name: &str, matching_func: MatchingFunction){
-        self.has_pattern = true;
-        self.matching_function = Some(matching_func);
+    fn names(&self, roles: &[usize]) -> Vec<String> {
+        roles.iter().map(|&role| self.roles[role].name.clone()).collect()
+    }
+}
+
+fn domain_fields(domain: Option<&str>) -> Vec<String> {
+    domain.into_iter().map(|domain| domain.to_string()).collect()
+}
+
+/// The name of a role prefixed by its domain, for error messages.
+fn full_name(name: &str, domain: &[String]) -> String {
+    if domain.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", domain.join("::"), name)
+    }
+}
+
+fn graph_domain(domain: &[String]) -> Option<String> {
+    if domain.is_empty() {
+        None
+    } else {
+        Some(domain.join(", "))
     }
 }
 
@@ -320,6 +498,11 @@ mod tests {
         assert_eq!(manager.has_link("u4", "g1", Some("domain2")), false);
         assert_eq!(manager.has_link("u4", "admin", Some("domain1")), true);
         assert_eq!(manager.has_link("u4", "admin", Some("domain2")), false);
+
+        assert_eq!(manager.get_roles("u1", Some("domain1")), ["g1"]);
+        assert_eq!(manager.get_users("admin", Some("domain1")), ["u4"]);
+        assert_eq!(manager.get_implicit_roles("u4", Some("domain1")), ["admin"]);
+        assert_eq!(manager.get_roles("u1", Some("domain2")), Vec::<String>::new());
     }
 
     #[test]
@@ -354,4 +537,107 @@ mod tests {
         assert_eq!(manager.has_link("u4", "g2", None), false);
         assert_eq!(manager.has_link("u4", "g3", None), false);
     }
+
+    #[test]
+    fn test_cycle_detection() {
+        let mut manager = DefaultRoleManager::new(10);
+        manager.add_link("u1", "g1", None).unwrap();
+        manager.add_link("g1", "g2", None).unwrap();
+        manager.add_link("g2", "g3", None).unwrap();
+
+        assert!(matches!(manager.add_link("g3", "u1", None), Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))));
+        assert!(matches!(manager.add_link("g1", "g1", None), Err(Error::RoleManager(RoleManagerError::RoleCycle(..)))));
+
+        // The same names in another domain are unrelated.
+        manager.add_link("g3", "u1", Some("domain1")).unwrap();
+
+        assert_eq!(manager.has_link("u1", "g3", None), true);
+        assert_eq!(manager.has_link("g3", "u1", None), false);
+        assert_eq!(manager.get_roles("g3", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_implicit_roles() {
+        let mut manager = DefaultRoleManager::new(10);
+        manager.add_link("u1", "g1", None).unwrap();
+        manager.add_link("g1", "g2", None).unwrap();
+        manager.add_link("g1", "g3", None).unwrap();
+        manager.add_link("g2", "g4", None).unwrap();
+
+        assert_eq!(manager.get_implicit_roles("u1", None), ["g1", "g2", "g3", "g4"]);
+        assert_eq!(manager.has_link("u1", "g4", None), true);
+
+        // Cached closures must follow later changes to the graph.
+        manager.delete_link("g1", "g2", None).unwrap();
+        assert_eq!(manager.get_implicit_roles("u1", None), ["g1", "g3"]);
+        assert_eq!(manager.has_link("u1", "g4", None), false);
+
+        manager.add_link("g3", "g4", None).unwrap();
+        assert_eq!(manager.get_implicit_roles("u1", None), ["g1", "g3", "g4"]);
+        assert_eq!(manager.has_link("u1", "g4", None), true);
+
+        manager.clear().unwrap();
+        assert_eq!(manager.get_implicit_roles("u1", None), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_max_hierarchy_level() {
+        let mut manager = DefaultRoleManager::new(2);
+        manager.add_link("u1", "g1", None).unwrap();
+        manager.add_link("g1", "g2", None).unwrap();
+        manager.add_link("g2", "g3", None).unwrap();
+
+        assert_eq!(manager.has_link("u1", "g2", None), true);
+        assert_eq!(manager.has_link("u1", "g3", None), false);
+        assert_eq!(manager.has_link("g1", "g3", None), true);
+        assert_eq!(manager.get_users("g1", None), ["u1"]);
+    }
+
+    #[test]
+    fn test_role_graph_names() {
+        let mut manager = DefaultRoleManager::new(10);
+        manager.add_link("a::b", "c", Some("d")).unwrap();
+        manager.add_link("b", "c", Some("d::a")).unwrap();
+
+        let graph = manager.get_role_graph();
+        assert_eq!(
+            graph.links,
+            vec![
+                RoleLink {
+                    user: "a::b".to_string(),
+                    role: "c".to_string(),
+                    domain: Some("d".to_string()),
+                },
+                RoleLink {
+                    user: "b".to_string(),
+                    role: "c".to_string(),
+                    domain: Some("d::a".to_string()),
+                },
+            ]
+        );
+        assert_eq!(graph.nodes.len(), 4);
+        assert_eq!(manager.has_link("b", "c", Some("d")), false);
+    }
+
+    #[test]
+    fn test_domain_fields() {
+        let mut manager = DefaultRoleManager::new(10);
+        manager.add_link_with_fields("u1", "admin", &["a", "b"]).unwrap();
+        manager.add_link("u2", "admin", Some("a::b")).unwrap();
+
+        assert_eq!(manager.has_link_with_fields("u1", "admin", &["a", "b"]), true);
+        assert_eq!(manager.has_link_with_fields("u1", "admin", &["a::b"]), false);
+        assert_eq!(manager.has_link("u1", "admin", Some("a::b")), false);
+        assert_eq!(manager.has_link_with_fields("u2", "admin", &["a::b"]), true);
+        assert_eq!(manager.has_link_with_fields("u2", "admin", &["a", "b"]), false);
+        assert_eq!(manager.get_implicit_roles_with_fields("u1", &["a", "b"]), ["admin"]);
+        assert_eq!(
+            manager.get_implicit_roles_with_fields("u2", &["a", "b"]),
+            Vec::<String>::new()
+        );
+
+        manager.delete_link_with_fields("u1", "admin", &["a", "b"]).unwrap();
+        assert_eq!(manager.has_link_with_fields("u1", "admin", &["a", "b"]), false);
+        assert_eq!(manager.has_link("u2", "admin", Some("a::b")), true);
+    }
 }
@@ -0,0 +1,198 @@
+use std::fmt;
+
+/// A role known to a role manager.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoleNode {
+    pub name: String,
+    /// The domain of the role, the fields of a domain made of several fields are joined with `, `.
+    pub domain: Option<String>,
+}
+
+/// An inheritance link such that `user` inherits `role` in `domain`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RoleLink {
+    pub user: String,
+    pub role: String,
+    pub domain: Option<String>,
+}
+
+/// A snapshot of the roles and inheritance links stored in a role manager.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RoleGraph {
+    pub nodes: Vec<RoleNode>,
+    pub links: Vec<RoleLink>,
+}
+
+impl RoleGraph {
+    /// Create an empty RoleGraph.
+    pub fn new() -> Self {
+        RoleGraph::default()
+    }
+
+    /// Get the list of domains used by the roles, in order of appearance.
+    pub fn domains(&self) -> Vec<String> {
+        let mut domains: Vec<String> = Vec::new();
+        for node in &self.nodes {
+            if let Some(domain) = &node.domain {
+                if !domains.contains(domain) {
+                    domains.push(domain.clone());
+                }
+            }
+        }
+        domains
+    }
+
+    /// Render the graph in the DOT language of Graphviz, with one cluster per domain.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph roles {\n");
+
+        for domain in self.domains() {
+            dot.push_str(&format!("    subgraph \"cluster_{}\" {{\n", escape(&domain)));
+            dot.push_str(&format!("        label = \"{}\";\n", escape(&domain)));
+            for node in self.nodes.iter().filter(|n| n.domain.as_ref() == Some(&domain)) {
+                dot.push_str(&format!(
+                    "        \"{}\" [label = \"{}\"];\n",
+                    escape(&node_id(&node.name, &node.domain)),
+                    escape(&node.name)
+                ));
+            }
+            dot.push_str("    }\n");
+        }
+
+        for node in self.nodes.iter().filter(|n| n.domain.is_none()) {
+            dot.push_str(&format!("    \"{}\";\n", escape(&node.name)));
+        }
+
+        for link in &self.links {
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                escape(&node_id(&link.user, &link.domain)),
+                escape(&node_id(&link.role, &link.domain))
+            ));
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Render the graph as a JSON document with `nodes`, `links` and `domains` arrays.
+    pub fn to_json(&self) -> String {
+        let nodes: Vec<String> = self
+            .nodes
+            .iter()
+            .map(|n| format!("{{\"name\":{},\"domain\":{}}}", json_string(&n.name), json_option(&n.domain)))
+            .collect();
+        let links: Vec<String> = self
+            .links
+            .iter()
+            .map(|l| {
+                format!(
+                    "{{\"user\":{},\"role\":{},\"domain\":{}}}",
+                    json_string(&l.user),
+                    json_string(&l.role),
+                    json_option(&l.domain)
+                )
+            })
+            .collect();
+        let domains: Vec<String> = self.domains().iter().map(|d| json_string(d)).collect();
+
+        format!(
+            "{{\"nodes\":[{}],\"links\":[{}],\"domains\":[{}]}}",
+            nodes.join(","),
+            links.join(","),
+            domains.join(",")
+        )
+    }
+}
+
+impl fmt::Display for RoleGraph {
+    /// Write one line per role with the roles it inherits, e.g. `alice < admin, user`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for node in &self.nodes {
+            let roles: Vec<&str> = self
+                .links
+                .iter()
+                .filter(|l| l.user == node.name && l.domain == node.domain)
+                .map(|l| l.role.as_str())
+                .collect();
+            if roles.is_empty() {
+                continue;
+            }
+            match &node.domain {
+                Some(domain) => writeln!(f, "{}::{} < {}", domain, node.name, roles.join(", "))?,
+                None => writeln!(f, "{} < {}", node.name, roles.join(", "))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn node_id(name: &str, domain: &Option<String>) -> String {
+    match domain {
+        Some(domain) => format!("{}::{}", domain, name),
+        None => name.to_string(),
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn json_string(s: &str) -> String {
+    let mut res = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if (c as u32) < 0x20 => res.push_str(&format!("\\u{:04x}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn json_option(s: &Option<String>) -> String {
+    match s {
+        Some(s) => json_string(s),
+        None => "null".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rbac::{DefaultRoleManager, RoleLink, RoleManager};
+
+    #[test]
+    fn test_role_graph() {
+        let mut manager = DefaultRoleManager::new(10);
+        manager.add_link("u1", "g1", None).unwrap();
+        manager.add_link("g1", "g2", None).unwrap();
+        manager.add_link("alice", "admin", Some("domain1")).unwrap();
+
+        let graph = manager.get_role_graph();
+        assert_eq!(graph.nodes.len(), 5);
+        assert_eq!(graph.domains(), ["domain1"]);
+        assert_eq!(
+            graph.links[2],
+            RoleLink {
+                user: "alice".to_string(),
+                role: "admin".to_string(),
+                domain: Some("domain1".to_string()),
+            }
+        );
+
+        assert_eq!(graph.to_string(), "u1 < g1\ng1 < g2\ndomain1::alice < admin\n");
+        assert_eq!(
+            graph.to_dot(),
+            "digraph roles {\n    subgraph \"cluster_domain1\" {\n        label = \"domain1\";\n        \"domain1::alice\" [label = \"alice\"];\n        \"domain1::admin\" [label = \"admin\"];\n    }\n    \"u1\";\n    \"g1\";\n    \"g2\";\n    \"u1\" -> \"g1\";\n    \"g1\" -> \"g2\";\n    \"domain1::alice\" -> \"domain1::admin\";\n}\n"
+        );
+        assert_eq!(
+            graph.to_json(),
+            r#"{"nodes":[{"name":"u1","domain":null},{"name":"g1","domain":null},{"name":"g2","domain":null},{"name":"alice","domain":"domain1"},{"name":"admin","domain":"domain1"}],"links":[{"user":"u1","role":"g1","domain":null},{"user":"g1","role":"g2","domain":null},{"user":"alice","role":"admin","domain":"domain1"}],"domains":["domain1"]}"#
+        );
+    }
+}
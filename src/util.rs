@@ -5,6 +5,7 @@ use regex::Regex;
 
 lazy_static! {
     static ref REGEX_ESCAPE: Regex = Regex::new(r"(^|[\s|&><+\-*/\(\)|!,]+)([r|p])(\.)").unwrap();
+    static ref REGEX_UNESCAPE: Regex = Regex::new(r"(^|[\s|&><+\-*/\(\)|!,]+)([rp])_").unwrap();
 }
 
 pub fn escape_assertion(s: &str) -> String {
@@ -12,28 +13,108 @@ pub fn escape_assertion(s: &str) -> String {
     e.replace("%", "_")
 }
 
+/// Revert `escape_assertion`, e.g. `r_sub == p_sub` becomes `r.sub == p.sub`.
+pub fn unescape_assertion(s: &str) -> String {
+    REGEX_UNESCAPE.replace_all(s, "$1$2.").to_string()
+}
+
+/// Remove the comment starting at the first `#` of `s`, see `comment_start`.
 pub fn remove_comments(s: &str) -> &str {
-    if let Some(pos) = s.find('#') {
-        &s[0..pos].trim()
-    } else {
-        s
+    match comment_start(s) {
+        Some(pos) => s[..pos].trim(),
+        None => s,
     }
 }
 
-pub fn array_equals(a: &Vec<String>, b: &Vec<String>) -> bool{
-    let a_size = a.len();
-
-    if a_size != b.len(){
-        return false;
+/// Get the position of the first `#` of `s` outside a `'` or `"` string literal, which starts a comment.
+pub fn comment_start(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => quote = Some(c),
+            None if c == '#' => return Some(i),
+            None => {}
+        }
     }
+    None
+}
 
-    for i in 0..a_size{
-        if a[i] != b[i]{
-            return false;
+/// Copy a rule, or any list of values, into owned strings.
+pub fn to_strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|s| s.to_string()).collect()
+}
+
+/// Split a line of CSV into its trimmed values.
+///
+/// Values may be enclosed in double quotes to contain commas, leading `#` or surrounding spaces, a
+/// double quote inside a quoted value is escaped by doubling it. Returns None if a quoted value is
+/// not terminated or followed by anything but a comma.
+pub fn parse_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut values = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        value.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => value.push(c),
+                    None => return None,
+                }
+            }
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            if chars.peek().is_some_and(|&c| c != ',') {
+                return None;
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c == ',' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+            value = value.trim_end().to_string();
+        }
+        values.push(value);
+
+        if chars.next().is_none() {
+            return Some(values);
         }
     }
+}
 
-    return true;
+/// Join values into a line of CSV, quoting the values that `parse_csv_line` could not read back
+/// otherwise.
+pub fn format_csv_line<S: AsRef<str>>(values: &[S]) -> String {
+    values
+        .iter()
+        .map(|value| {
+            let value = value.as_ref();
+            let needs_quotes =
+                value.contains([',', '"', '\n', '\r']) || value.starts_with('#') || value.trim() != value;
+            if needs_quotes {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
 }
 
 pub fn array_2_d_equals(a: &Vec<Vec<String>>, b: &Vec<Vec<String>>) -> bool{
@@ -44,7 +125,7 @@ pub fn array_2_d_equals(a: &Vec<Vec<String>>, b: &Vec<Vec<String>>) -> bool{
     }
 
     for i in 0..a_size{
-        if !array_equals(&a[i], &b[i]){
+        if a[i] != b[i]{
             return false;
         }
     }
@@ -127,6 +208,19 @@ fn test_escape_assertion() {
     );
 }
 
+#[test]
+fn test_unescape_assertion() {
+    let assertions = [
+        "r.sub == p.sub && r.obj == p.obj && r.act == p.act",
+        "g(r.sub, p.sub) && keyMatch(r.obj, p.obj) || r.sub == \"root\"",
+        "some(where (p.eft == allow)) && !some(where (p.eft == deny))",
+        "r.attr.value == p.attr",
+    ];
+    for assertion in &assertions {
+        assert_eq!(unescape_assertion(&escape_assertion(assertion)), *assertion);
+    }
+}
+
 #[test]
 fn test_remove_comments() {
     assert_eq!(remove_comments("r.act == p.act # comments"), "r.act == p.act");
@@ -134,12 +228,11 @@ fn test_remove_comments() {
     assert_eq!(remove_comments("r.act == p.act###"), "r.act == p.act");
     assert_eq!(remove_comments("### comments"), "");
     assert_eq!(remove_comments("r.act == p.act"), "r.act == p.act");
-}
-
-#[test]
-fn test_array_equals(){
-    assert_eq!(array_equals(&vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()], &vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()]), true);
-    assert_eq!(array_equals(&vec!["alice".to_owned(), "data1".to_owned(), "read".to_owned()], &vec!["alice".to_owned(), "data2".to_owned(), "read".to_owned()]), false);
+    assert_eq!(remove_comments(r#"r.obj == "a#b" # comments"#), r#"r.obj == "a#b""#);
+    assert_eq!(
+        remove_comments("r.obj == 'a#b' && r.act == 'c'#d"),
+        "r.obj == 'a#b' && r.act == 'c'"
+    );
 }
 
 #[test]
@@ -151,3 +244,32 @@ fn test_array_2_d_equals(){
     assert_eq!(array_2_d_equals(&vec![a.to_owned(), a.to_owned(), a.to_owned()], &vec![a.to_owned(), a.to_owned()]),false);
     assert_eq!(array_2_d_equals(&vec![a.to_owned(), b.to_owned()], &vec![b.to_owned(), a.to_owned()]),false);
 }
+
+#[test]
+fn test_parse_csv_line() {
+    assert_eq!(parse_csv_line("p, alice, data1, read").unwrap(), ["p", "alice", "data1", "read"]);
+    assert_eq!(parse_csv_line("p,alice ,  data1,read").unwrap(), ["p", "alice", "data1", "read"]);
+    assert_eq!(parse_csv_line("p, alice, , read").unwrap(), ["p", "alice", "", "read"]);
+    assert_eq!(
+        parse_csv_line(r#"p, alice, "/api/items?a=1,2", read"#).unwrap(),
+        ["p", "alice", "/api/items?a=1,2", "read"]
+    );
+    assert_eq!(
+        parse_csv_line(r##"p, "#admin", " data1 ", "say ""hi""""##).unwrap(),
+        ["p", "#admin", " data1 ", r#"say "hi""#]
+    );
+    assert_eq!(parse_csv_line(r#"p, alice, "data1"#), None);
+    assert_eq!(parse_csv_line(r#"p, alice, "data1" x, read"#), None);
+}
+
+#[test]
+fn test_format_csv_line() {
+    assert_eq!(format_csv_line(&["p", "alice", "data1", "read"]), "p, alice, data1, read");
+    assert_eq!(
+        format_csv_line(&["p", "#admin", " data1 ", "/api/items?a=1,2", r#"say "hi""#]),
+        r##"p, "#admin", " data1 ", "/api/items?a=1,2", "say ""hi""""##
+    );
+
+    let values = ["p", "#admin", "a, b", "", r#""quoted""#];
+    assert_eq!(parse_csv_line(&format_csv_line(&values)).unwrap(), values);
+}